@@ -0,0 +1,172 @@
+use readmine::response::TimeEntryActivity;
+use std::fmt;
+use std::io;
+use std::result;
+
+/// Errors of the command line tool, on top of those reported by the library.
+#[derive(Debug)]
+pub enum Error {
+    Readmine(readmine::Error),
+    ProjectDirs,
+    ConfigLoad(toml::de::Error),
+    ConfigSave(toml::ser::Error),
+    InvalidTimeEntryId(String),
+    InvalidNumber(String),
+    TimeEntriesNotDeleted(Vec<i32>),
+    CannotOpenTerminal,
+    Terminal(term::Error),
+    ChronoParse(chrono::ParseError),
+    InvalidActivityName(String, Vec<TimeEntryActivity>),
+    InvalidColumnName(String, Vec<String>),
+    InvalidOutputFormat(String),
+    InvalidGroupKey(String, Vec<String>),
+    InvalidImportFile(String),
+    ImportRejected(usize),
+    TimerAlreadyRunning,
+    TimerNotRunning,
+    TimerTooLong(f32),
+    UnknownProfile(String),
+    Keyring(String),
+    InvalidName(&'static str, String, Vec<String>),
+    Editor(String),
+    InvalidWeekStart(String),
+    InvalidRangeEpoch(String),
+}
+
+/// Result type returned throughout the command line tool.
+pub type Result<T> = result::Result<T, Error>;
+
+impl std::error::Error for Error {}
+
+impl From<readmine::Error> for Error {
+    fn from(error: readmine::Error) -> Self {
+        Error::Readmine(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Readmine(error.into())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Readmine(error.into())
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::ConfigLoad(error)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Error::ConfigSave(error)
+    }
+}
+
+impl From<term::Error> for Error {
+    fn from(error: term::Error) -> Self {
+        Error::Terminal(error)
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(error: chrono::ParseError) -> Self {
+        Error::ChronoParse(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Readmine(error) => write!(f, "{}", error),
+            Error::ProjectDirs => write!(f, "Cannot locate application config directory"),
+            Error::ConfigLoad(error) => write!(f, "Config reading error: {}", error),
+            Error::ConfigSave(error) => write!(f, "Config writing error: {}", error),
+            Error::InvalidTimeEntryId(arg) => write!(f, "Invalid time entry id: '{}'", arg),
+            Error::InvalidNumber(arg) => write!(f, "Invalid number: '{}'", arg),
+            Error::CannotOpenTerminal => write!(f, "Cannot open terminal interface"),
+            Error::Terminal(error) => write!(f, "Terminal error: {}", error),
+            Error::ChronoParse(error) => write!(f, "Date/time parse error: {}", error),
+            Error::InvalidActivityName(provided_name, activities) => {
+                let first_name = activities
+                    .first()
+                    .map(|a| a.name.clone())
+                    .unwrap_or_else(String::new);
+                let names = activities
+                    .iter()
+                    .skip(1)
+                    .fold(first_name, |names, a| format!("{}, {}", names, a.name));
+                write!(
+                    f,
+                    "Invalid activity name \"{}\". Available values: {}",
+                    provided_name, names
+                )
+            }
+            Error::TimeEntriesNotDeleted(ids) => {
+                let ids: Vec<_> = ids.iter().map(|id| format!("#{}", id)).collect();
+                write!(f, "Cannot delete time entries: {}", ids.join(", "))
+            }
+            Error::InvalidColumnName(provided_name, names) => write!(
+                f,
+                "Invalid column name \"{}\". Available values: {}",
+                provided_name,
+                names.join(", ")
+            ),
+            Error::InvalidOutputFormat(input) => write!(
+                f,
+                "Invalid output format \"{}\". Available values: table, json, csv, tsv",
+                input
+            ),
+            Error::InvalidGroupKey(provided_name, names) => write!(
+                f,
+                "Invalid grouping \"{}\". Available values: {}",
+                provided_name,
+                names.join(", ")
+            ),
+            Error::InvalidImportFile(reason) => write!(f, "Invalid import file: {}", reason),
+            Error::ImportRejected(count) => write!(f, "{} time entries rejected", count),
+            Error::TimerAlreadyRunning => write!(
+                f,
+                "Timer is already running. Use \"time stop\" or \"time cancel\" first."
+            ),
+            Error::TimerNotRunning => {
+                write!(f, "Timer is not running. Use \"time start\" to start it.")
+            }
+            Error::TimerTooLong(hours) => write!(
+                f,
+                "Timer has been running for {:.2}h, more than a day. \
+                 Use \"time cancel\" and log the time with \"time add\" instead.",
+                hours
+            ),
+            Error::UnknownProfile(name) => write!(
+                f,
+                "Unknown profile \"{}\". Use \"profile list\" to show available profiles.",
+                name
+            ),
+            Error::Keyring(message) => write!(f, "Keyring error: {}", message),
+            Error::InvalidName(kind, provided_name, names) => write!(
+                f,
+                "Invalid {} name \"{}\". Available values: {}",
+                kind,
+                provided_name,
+                names.join(", ")
+            ),
+            Error::Editor(message) => write!(f, "Editor error: {}", message),
+            Error::InvalidWeekStart(input) => write!(
+                f,
+                "Invalid first day of week \"{}\". Use a day name, e.g. mon, sun or sat",
+                input
+            ),
+            Error::InvalidRangeEpoch(input) => write!(
+                f,
+                "Invalid range_epoch setting \"{}\". Use a date, e.g. 2019-01-01",
+                input
+            ),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Local copies of lists that rarely change on the server, e.g. time entry
+//! activities or trackers, so that names can be resolved without a request.
+
+use crate::app_error::Result;
+use crate::config::cache_dir;
+use openssl::sha::sha256;
+use readmine::response::{Project, Reference, TimeEntryActivity};
+use readmine::{Credentials, RedmineClient};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::cell::RefCell;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ACTIVITIES: &str = "activities";
+const TRACKERS: &str = "trackers";
+const STATUSES: &str = "statuses";
+const PRIORITIES: &str = "priorities";
+const PROJECTS: &str = "projects";
+
+/// Item of a cached list which can be looked up by name.
+pub trait Named {
+    fn matches(&self, name: &str) -> bool;
+}
+
+impl Named for TimeEntryActivity {
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+impl Named for Reference {
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+impl Named for Project {
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.identifier == name
+            || self.id.to_string() == name
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile<T> {
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+    items: T,
+}
+
+pub struct Cache<'a> {
+    client: &'a RedmineClient,
+    dir: PathBuf,
+    ttl: Duration,
+    page_size: u32,
+    /// Lists fetched during this run, which are not fetched again.
+    fetched: RefCell<Vec<&'static str>>,
+}
+
+impl<'a> Cache<'a> {
+    /// Cache of the server `client` connects to, separate for each user as the lists
+    /// depend on their permissions. Lists older than `ttl` are fetched again.
+    pub fn new(client: &'a RedmineClient, ttl: Duration, page_size: u32) -> Result<Self> {
+        Ok(Cache {
+            client,
+            dir: cache_dir()?
+                .join(server_dir_name(client.url().as_str()))
+                .join(user_dir_name(client.credentials())),
+            ttl,
+            page_size,
+            fetched: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Time entry activities, fetched again if any of `names` is missing in the cached list.
+    pub async fn activities(&self, names: &[&str]) -> Result<Vec<TimeEntryActivity>> {
+        self.list(ACTIVITIES, names, || self.client.time_entry_activities())
+            .await
+    }
+
+    pub async fn trackers(&self, names: &[&str]) -> Result<Vec<Reference>> {
+        self.list(TRACKERS, names, || self.client.trackers()).await
+    }
+
+    pub async fn statuses(&self, names: &[&str]) -> Result<Vec<Reference>> {
+        self.list(STATUSES, names, || self.client.issue_statuses())
+            .await
+    }
+
+    pub async fn priorities(&self, names: &[&str]) -> Result<Vec<Reference>> {
+        self.list(PRIORITIES, names, || self.client.issue_priorities())
+            .await
+    }
+
+    /// Projects, looked up by name, identifier or id.
+    pub async fn projects(&self, names: &[&str]) -> Result<Vec<Project>> {
+        self.list(PROJECTS, names, || self.client.projects(self.page_size))
+            .await
+    }
+
+    /// Fetches all lists again.
+    pub async fn refresh(&self) -> Result<()> {
+        self.save(ACTIVITIES, &self.client.time_entry_activities().await?)?;
+        self.save(TRACKERS, &self.client.trackers().await?)?;
+        self.save(STATUSES, &self.client.issue_statuses().await?)?;
+        self.save(PRIORITIES, &self.client.issue_priorities().await?)?;
+        self.save(PROJECTS, &self.client.projects(self.page_size).await?)?;
+        Ok(())
+    }
+
+    /// Returns the cached list, fetching it again if it expired or any of `names` is missing,
+    /// e.g. because it was added on the server. A list is fetched at most once per run.
+    async fn list<T, F, R>(
+        &self,
+        list_name: &'static str,
+        names: &[&str],
+        fetch: F,
+    ) -> Result<Vec<T>>
+    where
+        T: Named + Serialize + DeserializeOwned,
+        F: FnOnce() -> R,
+        R: Future<Output = readmine::Result<Vec<T>>>,
+    {
+        if let Some(file) = self.load::<T>(list_name) {
+            if contains_all(&file.items, names) || self.fetched.borrow().contains(&list_name) {
+                return Ok(file.items);
+            }
+        }
+        let items = fetch().await?;
+        self.fetched.borrow_mut().push(list_name);
+        self.save(list_name, &items)?;
+        Ok(items)
+    }
+
+    /// Returns the cached list unless it is missing, unreadable or expired.
+    fn load<T: DeserializeOwned>(&self, list_name: &str) -> Option<CacheFile<Vec<T>>> {
+        let contents = fs::read_to_string(self.path(list_name)).ok()?;
+        let file: CacheFile<Vec<T>> = serde_json::from_str(&contents).ok()?;
+        let age = now().checked_sub(file.fetched_at)?;
+        if age < self.ttl.as_secs() {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn save<T: Serialize>(&self, list_name: &str, items: &[T]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = CacheFile {
+            fetched_at: now(),
+            items,
+        };
+        fs::write(self.path(list_name), serde_json::to_string(&file)?)?;
+        Ok(())
+    }
+
+    fn path(&self, list_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", list_name))
+    }
+}
+
+fn contains_all<T: Named>(items: &[T], names: &[&str]) -> bool {
+    names
+        .iter()
+        .all(|name| items.iter().any(|item| item.matches(name)))
+}
+
+/// Removes cached lists of all servers.
+pub fn clear() -> Result<()> {
+    let dir = cache_dir()?;
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Directory name unique for the server URL, e.g. `redmine.example.com_tracker`.
+fn server_dir_name(url: &str) -> String {
+    let without_scheme = url.splitn(2, "://").last().unwrap_or(url);
+    without_scheme
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Directory name unique for the user the credentials belong to, without revealing them.
+fn user_dir_name(credentials: &Credentials) -> String {
+    let identity = match credentials {
+        Credentials::None => return "anonymous".to_string(),
+        Credentials::ApiKey(api_key) => format!("key:{}", api_key),
+        Credentials::Basic { login, .. } => format!("login:{}", login),
+    };
+    sha256(identity.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_dir_name_from_url() {
+        assert_eq!(
+            server_dir_name("https://example.com:8080/redmine/"),
+            "example.com_8080_redmine"
+        );
+    }
+
+    #[test]
+    fn user_dir_name_differs_by_user() {
+        let first = user_dir_name(&Credentials::ApiKey("first-key".to_string()));
+        let second = user_dir_name(&Credentials::ApiKey("second-key".to_string()));
+        assert_eq!(first.len(), 16);
+        assert!(!first.contains("first-key"));
+        assert_ne!(first, second);
+        assert_eq!(
+            first,
+            user_dir_name(&Credentials::ApiKey("first-key".to_string()))
+        );
+        assert_eq!(user_dir_name(&Credentials::None), "anonymous");
+    }
+
+    #[test]
+    fn contains_all_names() {
+        let items = vec![Reference {
+            id: 1,
+            name: "New".to_string(),
+        }];
+        assert!(contains_all(&items, &[]));
+        assert!(contains_all(&items, &["new"]));
+        assert!(!contains_all(&items, &["new", "Closed"]));
+    }
+
+    #[test]
+    fn project_matches_name_identifier_and_id() {
+        let project = Project {
+            id: 7,
+            name: "Web Site".to_string(),
+            identifier: "website".to_string(),
+        };
+        assert!(project.matches("web site"));
+        assert!(project.matches("website"));
+        assert!(project.matches("7"));
+        assert!(!project.matches("8"));
+    }
+}
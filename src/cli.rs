@@ -1,10 +1,29 @@
-use clap::{App, AppSettings, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgGroup, SubCommand};
 
 pub fn build_cli() -> App<'static, 'static> {
     App::new("readmine")
         .setting(AppSettings::SubcommandRequired)
         .version("0.1")
         .about("Redmine client")
+        .arg(Arg::with_name("profile")
+            .help("name of the server profile to use")
+            .long("profile")
+            .env("READMINE_PROFILE")
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("server_url")
+            .help("address of the Redmine server, overrides the profile")
+            .long("url")
+            .env("READMINE_URL")
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("api_key")
+            .help("API key, overrides the profile")
+            .long("api-key")
+            .env("READMINE_API_KEY")
+            .hide_env_values(true)
+            .global(true)
+            .takes_value(true))
         .subcommand(SubCommand::with_name("login")
                     .about("login to the Redmine server")
                     .arg(Arg::with_name("url")
@@ -13,26 +32,282 @@ pub fn build_cli() -> App<'static, 'static> {
                         .required(true))
                     .arg(Arg::with_name("name")
                         .help("user login name")
-                        .index(2)))
+                        .index(2))
+                    .arg(Arg::with_name("password_stdin")
+                        .help("read the password from the standard input instead of the terminal")
+                        .long("password-stdin"))
+                    .after_help("Use the global --api-key option (or READMINE_API_KEY) \
+                                 to log in with an existing API key instead of a password."))
         .subcommand(SubCommand::with_name("logout")
                     .about("log out of the Redmine server"))
+        .subcommand(SubCommand::with_name("profile")
+                    .about("manage server profiles")
+                    .setting(AppSettings::SubcommandRequired)
+                    .subcommand(SubCommand::with_name("list")
+                        .about("show all profiles"))
+                    .subcommand(SubCommand::with_name("use")
+                        .about("make a profile the default one")
+                        .arg(Arg::with_name("name").index(1).required(true)))
+                    .subcommand(SubCommand::with_name("remove")
+                        .about("remove a profile")
+                        .arg(Arg::with_name("name").index(1).required(true))))
         .subcommand(SubCommand::with_name("user")
                     .about("show user info"))
         .subcommand(SubCommand::with_name("time")
                     .about("show/add time entries")
                     .arg(Arg::with_name("range")
                         .help("time range for showing time ranges: 2019-01-23..2019-05-09, \
+                               today, yesterday, day-2 (the day before yesterday), \
                                week (current week), \
                                month (current month), \
+                               quarter (current quarter), year (current year), \
                                week-1 (last week), week-2 (the week before last),
-                               month-1 (last month),
-                               month-1..week-1 (from the beginning of last month to the end of last week) etc.")
+                               month-1 (last month), quarter-1, year-1,
+                               mon, fri (days of the current week, possibly still ahead),
+                               last-fri (the most recent Friday before today),
+                               2019-W34 (ISO week),
+                               last-7d, last-4w, last-3m (rolling windows ending today),
+                               month-1..week-1 (from the beginning of last month to the end of last week),
+                               2019-01-23.. (until today), ..2019-05-09 (since the range_epoch setting) etc.")
                         .default_value("week")
                         .index(1))
+                    .arg(Arg::with_name("columns")
+                        .help("comma separated list of columns to show: \
+                               id, date, hours, issue, project, activity, user, comment, created, updated")
+                        .long("columns")
+                        .takes_value(true))
+                    .arg(Arg::with_name("format")
+                        .help("output format")
+                        .long("format")
+                        .possible_values(&["table", "json", "csv", "tsv"])
+                        .default_value("table"))
+                    .arg(Arg::with_name("group_by")
+                        .help("comma separated list of groupings for showing subtotals: \
+                               project, issue, activity, day, week, month, e.g. \"project,activity\"")
+                        .long("group-by")
+                        .takes_value(true))
+                    .arg(Arg::with_name("week_start")
+                        .help("first day of week, e.g. sun; overrides the week_start setting")
+                        .long("week-start")
+                        .takes_value(true))
                     .subcommand(SubCommand::with_name("add")
                         .arg(Arg::with_name("date").index(1).required(true))
                         .arg(Arg::with_name("hours").index(2).required(true))
                         .arg(Arg::with_name("issue_id").index(3).required(true))
                         .arg(Arg::with_name("activity").index(4).required(true))
-                        .arg(Arg::with_name("comment").index(5))))
+                        .arg(Arg::with_name("comment").index(5)))
+                    .subcommand(SubCommand::with_name("edit")
+                        .about("change an existing time entry")
+                        .arg(Arg::with_name("id")
+                            .help("id of the time entry")
+                            .index(1)
+                            .required(true))
+                        .arg(Arg::with_name("date").long("date").takes_value(true))
+                        .arg(Arg::with_name("hours").long("hours").takes_value(true))
+                        .arg(Arg::with_name("issue_id").long("issue").takes_value(true))
+                        .arg(Arg::with_name("activity").long("activity").takes_value(true))
+                        .arg(Arg::with_name("comment").long("comment").takes_value(true))
+                        .group(ArgGroup::with_name("changes")
+                            .args(&["date", "hours", "issue_id", "activity", "comment"])
+                            .multiple(true)
+                            .required(true)))
+                    .subcommand(SubCommand::with_name("delete")
+                        .about("delete time entries")
+                        .arg(Arg::with_name("id")
+                            .help("ids of the time entries")
+                            .index(1)
+                            .multiple(true)
+                            .required(true))
+                        .arg(Arg::with_name("yes")
+                            .help("do not ask for confirmation")
+                            .short("y")
+                            .long("yes")))
+                    .subcommand(SubCommand::with_name("import")
+                        .about("add time entries from a CSV or JSON file")
+                        .arg(Arg::with_name("file")
+                            .help("CSV file with a header row or JSON array of objects, \
+                                   with date, hours, issue, activity and optional comment fields")
+                            .index(1)
+                            .required(true))
+                        .arg(Arg::with_name("dry_run")
+                            .help("only validate the entries, do not add them")
+                            .long("dry-run")))
+                    .subcommand(SubCommand::with_name("start")
+                        .about("start a timer for logging time")
+                        .arg(Arg::with_name("issue_id").index(1).required(true))
+                        .arg(Arg::with_name("activity").index(2).required(true))
+                        .arg(Arg::with_name("comment").index(3)))
+                    .subcommand(SubCommand::with_name("status")
+                        .about("show the running timer"))
+                    .subcommand(SubCommand::with_name("stop")
+                        .about("stop the timer and log the elapsed time")
+                        .arg(Arg::with_name("yes")
+                            .help("do not ask for confirmation if the timer was started before midnight")
+                            .short("y")
+                            .long("yes")))
+                    .subcommand(SubCommand::with_name("cancel")
+                        .about("stop the timer without logging time")))
+        .subcommand(SubCommand::with_name("issues")
+                    .about("list issues")
+                    .arg(Arg::with_name("project")
+                        .help("project id or identifier")
+                        .short("p")
+                        .long("project")
+                        .takes_value(true))
+                    .arg(Arg::with_name("assignee")
+                        .help("id of the assigned user or \"me\"")
+                        .long("assignee")
+                        .takes_value(true))
+                    .arg(Arg::with_name("status")
+                        .help("open, closed, all or a status id")
+                        .long("status")
+                        .default_value("open"))
+                    .arg(Arg::with_name("tracker")
+                        .help("tracker id")
+                        .long("tracker")
+                        .takes_value(true))
+                    .arg(Arg::with_name("version")
+                        .help("target version id")
+                        .long("version")
+                        .takes_value(true))
+                    .arg(Arg::with_name("priority")
+                        .help("priority id")
+                        .long("priority")
+                        .takes_value(true))
+                    .arg(Arg::with_name("subject")
+                        .help("text the subject contains")
+                        .long("subject")
+                        .takes_value(true))
+                    .arg(Arg::with_name("sort")
+                        .help("sort order, e.g. \"priority:desc,updated_on\"")
+                        .long("sort")
+                        .takes_value(true))
+                    .arg(Arg::with_name("limit")
+                        .help("maximum number of issues to show")
+                        .long("limit")
+                        .default_value("25"))
+                    .arg(Arg::with_name("offset")
+                        .help("number of issues to skip")
+                        .long("offset")
+                        .default_value("0"))
+                    .arg(Arg::with_name("columns")
+                        .help("comma separated list of columns to show: \
+                               id, project, tracker, status, priority, assignee, author, version, \
+                               subject, done, start, due, estimated, created, updated")
+                        .long("columns")
+                        .takes_value(true))
+                    .arg(Arg::with_name("format")
+                        .help("output format")
+                        .long("format")
+                        .possible_values(&["table", "json", "csv", "tsv"])
+                        .default_value("table")))
+        .subcommand(SubCommand::with_name("issue")
+                    .about("show/create issues")
+                    .setting(AppSettings::SubcommandRequired)
+                    .subcommand(SubCommand::with_name("show")
+                        .about("show an issue with its description and history")
+                        .arg(Arg::with_name("issue_id").index(1).required(true)))
+                    .subcommand(SubCommand::with_name("new")
+                        .about("create an issue")
+                        .arg(Arg::with_name("project")
+                            .help("project id or identifier")
+                            .short("p")
+                            .long("project")
+                            .takes_value(true)
+                            .required(true))
+                        .arg(Arg::with_name("tracker")
+                            .help("tracker name, e.g. \"Bug\"")
+                            .short("t")
+                            .long("tracker")
+                            .takes_value(true)
+                            .required(true))
+                        .arg(Arg::with_name("subject")
+                            .short("s")
+                            .long("subject")
+                            .takes_value(true)
+                            .required(true))
+                        .arg(Arg::with_name("description")
+                            .help("description text, \"-\" reads it from the standard input")
+                            .short("d")
+                            .long("description")
+                            .takes_value(true)
+                            .allow_hyphen_values(true))
+                        .arg(Arg::with_name("edit")
+                            .help("write the description in $EDITOR")
+                            .short("e")
+                            .long("edit")
+                            .conflicts_with("description"))
+                        .arg(Arg::with_name("status")
+                            .help("status name, e.g. \"New\"")
+                            .long("status")
+                            .takes_value(true))
+                        .arg(Arg::with_name("priority")
+                            .help("priority name, e.g. \"High\"")
+                            .long("priority")
+                            .takes_value(true))
+                        .arg(Arg::with_name("assignee")
+                            .help("id of the assigned user or \"me\"")
+                            .long("assignee")
+                            .takes_value(true))
+                        .arg(Arg::with_name("parent")
+                            .help("parent issue id")
+                            .long("parent")
+                            .takes_value(true))
+                        .arg(Arg::with_name("start")
+                            .help("start date, e.g. 2019-08-19")
+                            .long("start")
+                            .takes_value(true))
+                        .arg(Arg::with_name("due")
+                            .help("due date, e.g. 2019-08-23")
+                            .long("due")
+                            .takes_value(true))
+                        .arg(Arg::with_name("estimated")
+                            .help("estimated time in hours")
+                            .long("estimated")
+                            .takes_value(true)))
+                    .subcommand(SubCommand::with_name("update")
+                        .about("change an issue and optionally log time on it")
+                        .arg(Arg::with_name("issue_id").index(1).required(true))
+                        .arg(Arg::with_name("status")
+                            .help("status name, e.g. \"Closed\"")
+                            .long("status")
+                            .takes_value(true))
+                        .arg(Arg::with_name("assignee")
+                            .help("id of the assigned user or \"me\"")
+                            .long("assignee")
+                            .takes_value(true))
+                        .arg(Arg::with_name("done")
+                            .help("done ratio in percent")
+                            .long("done")
+                            .takes_value(true))
+                        .arg(Arg::with_name("version")
+                            .help("target version id")
+                            .long("version")
+                            .takes_value(true))
+                        .arg(Arg::with_name("note")
+                            .help("comment added to the issue history")
+                            .short("n")
+                            .long("note")
+                            .takes_value(true))
+                        .arg(Arg::with_name("log")
+                            .help("hours to log on the issue for today")
+                            .long("log")
+                            .takes_value(true)
+                            .requires("activity"))
+                        .arg(Arg::with_name("activity")
+                            .help("activity of the logged time")
+                            .long("activity")
+                            .takes_value(true)
+                            .requires("log"))
+                        .group(ArgGroup::with_name("changes")
+                            .args(&["status", "assignee", "done", "version", "note", "log"])
+                            .multiple(true)
+                            .required(true))))
+        .subcommand(SubCommand::with_name("cache")
+                    .about("manage the local copy of activities, trackers, statuses, priorities and projects")
+                    .setting(AppSettings::SubcommandRequired)
+                    .subcommand(SubCommand::with_name("refresh")
+                        .about("fetch all cached lists again"))
+                    .subcommand(SubCommand::with_name("clear")
+                        .about("remove all cached lists")))
 }
@@ -1,35 +1,270 @@
-use crate::error::Error;
-use crate::result::Result;
+use crate::app_error::Error;
+use crate::app_error::Result;
+use crate::cache::Cache;
+use crate::keyring::{self, SecretStoreKind};
+use chrono::{NaiveDate, Weekday};
 use directories::ProjectDirs;
+use readmine::constants::{DATE_FORMAT, MAX_PAGE_SIZE};
+use readmine::request::RedmineClient;
+use readmine::time_range::RangeSettings;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{create_dir_all, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::time::Duration;
 use toml;
 
 const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_PROFILE: &str = "default";
+const DEFAULT_CACHE_TTL: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialStore {
+    Config,
+    Keyring,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Config {
+pub struct Profile {
+    pub url: String,
+    pub api_key: Option<String>,
+    /// Name under which the API key is kept in the keyring instead of `api_key`.
+    pub api_key_secret: Option<String>,
+    /// Where the API key referenced by `api_key_secret` is kept, the Secret Service if not set.
+    pub secret_store: Option<SecretStoreKind>,
+}
+
+impl Profile {
+    pub fn api_key(&self) -> Result<Option<String>> {
+        match &self.api_key_secret {
+            Some(reference) => {
+                keyring::lookup_api_key(&*keyring::open(self.secret_store())?, reference).map(Some)
+            }
+            None => Ok(self.api_key.clone()),
+        }
+    }
+
+    pub fn forget_api_key(&self) -> Result<()> {
+        match &self.api_key_secret {
+            Some(reference) => keyring::open(self.secret_store())?.delete(reference),
+            None => Ok(()),
+        }
+    }
+
+    fn secret_store(&self) -> SecretStoreKind {
+        self.secret_store.unwrap_or(SecretStoreKind::SecretService)
+    }
+}
+
+/// Settings given on the command line or in the environment, which take
+/// precedence over the config file: `--profile`/`READMINE_PROFILE`,
+/// `--url`/`READMINE_URL` and `--api-key`/`READMINE_API_KEY`.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub profile: Option<String>,
     pub url: Option<String>,
     pub api_key: Option<String>,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    pub page_size: Option<u32>,
+    pub timer_rounding: Option<f32>,
+    /// How long fetched activities, trackers etc. are cached, in seconds.
+    pub cache_ttl: Option<u64>,
+    /// First day of week, e.g. "sun". Monday if not set.
+    pub week_start: Option<String>,
+    /// Start of open-ended time ranges such as "..today", e.g. "2019-01-01".
+    pub range_epoch: Option<String>,
+    pub credential_store: Option<CredentialStore>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default, skip_serializing)]
+    url: Option<String>,
+    #[serde(default, skip_serializing)]
+    api_key: Option<String>,
+    #[serde(skip)]
+    selected_profile: Option<String>,
+    #[serde(skip)]
+    url_override: Option<String>,
+    #[serde(skip)]
+    api_key_override: Option<String>,
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         if let Ok(mut file) = File::open(config_dir()?.join(CONFIG_FILE)) {
             let mut contents = String::new();
             file.read_to_string(&mut contents)?;
-            let config = toml::from_str(&contents)?;
+            let mut config: Config = toml::from_str(&contents)?;
+            if config.migrate_flat_config() {
+                config.save()?;
+            }
             Ok(config)
         } else {
-            Ok(Self {
-                url: None,
-                api_key: None,
-            })
+            Ok(Self::default())
         }
     }
 
+    /// Moves the server details of the single-server config format
+    /// into the default profile.
+    fn migrate_flat_config(&mut self) -> bool {
+        let api_key = self.api_key.take();
+        if let Some(url) = self.url.take() {
+            self.profiles
+                .entry(DEFAULT_PROFILE.to_string())
+                .or_insert(Profile {
+                    url,
+                    api_key,
+                    api_key_secret: None,
+                    secret_store: None,
+                });
+            if self.default_profile.is_none() {
+                self.default_profile = Some(DEFAULT_PROFILE.to_string());
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn apply(&mut self, overrides: Overrides) {
+        self.select_profile(overrides.profile);
+        self.url_override = overrides.url;
+        self.api_key_override = overrides.api_key;
+    }
+
+    pub fn select_profile(&mut self, name: Option<String>) {
+        self.selected_profile = name;
+    }
+
+    pub fn profile_name(&self) -> &str {
+        self.selected_profile
+            .as_deref()
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiles.get(self.profile_name())
+    }
+
+    /// Creates the selected profile's server details, storing the API key
+    /// according to the `credential_store` setting. The keyring setting falls
+    /// back to an encrypted file if there is no secret service to use.
+    pub fn new_profile(&self, url: String, api_key: String) -> Result<Profile> {
+        match self.credential_store.unwrap_or(CredentialStore::Config) {
+            CredentialStore::Config => Ok(Profile {
+                url,
+                api_key: Some(api_key),
+                api_key_secret: None,
+                secret_store: None,
+            }),
+            CredentialStore::Keyring => {
+                let reference = self.profile_name().to_string();
+                let secret_store = keyring::available_store();
+                keyring::open(secret_store)?.store(&reference, &api_key)?;
+                Ok(Profile {
+                    url,
+                    api_key: None,
+                    api_key_secret: Some(reference),
+                    secret_store: Some(secret_store),
+                })
+            }
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        let name = self.profile_name().to_string();
+        if self.default_profile.is_none() {
+            self.default_profile = Some(name.clone());
+        }
+        self.profiles.insert(name, profile);
+    }
+
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        if self.profiles.contains_key(name) {
+            self.default_profile = Some(name.to_string());
+            Ok(())
+        } else {
+            Err(Error::UnknownProfile(name.to_string()))
+        }
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Result<Profile> {
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| Error::UnknownProfile(name.to_string()))?;
+        if self.default_profile.as_deref() == Some(name) {
+            self.default_profile = None;
+        }
+        Ok(profile)
+    }
+
+    /// Returns the server URL and API key to use. Overrides take precedence
+    /// over the selected profile, but the profile's API key is never sent
+    /// to an overridden URL of a different server.
+    pub fn server(&self) -> Result<Option<(String, Option<String>)>> {
+        let profile = self.profile();
+        let url = match (&self.url_override, profile) {
+            (Some(url), _) => url.clone(),
+            (None, Some(profile)) => profile.url.clone(),
+            (None, None) => return Ok(None),
+        };
+        let api_key = match (&self.api_key_override, profile) {
+            (Some(api_key), _) => Some(api_key.clone()),
+            (None, Some(profile)) if profile.url == url => profile.api_key()?,
+            (None, _) => None,
+        };
+        Ok(Some((url, api_key)))
+    }
+
+    pub fn client(&self) -> Result<Option<RedmineClient>> {
+        if let Some((url, api_key)) = self.server()? {
+            Ok(Some(RedmineClient::new(&url, api_key.into())?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+            .unwrap_or(MAX_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn week_start(&self) -> Result<Weekday> {
+        self.week_start
+            .as_deref()
+            .map_or(Ok(Weekday::Mon), parse_week_start)
+    }
+
+    /// Settings for parsing time ranges, with the week start given on the command line if any.
+    pub fn range_settings(&self, week_start: Option<Weekday>) -> Result<RangeSettings> {
+        let week_start = match week_start {
+            Some(week_start) => week_start,
+            None => self.week_start()?,
+        };
+        let mut settings = RangeSettings {
+            week_start,
+            ..RangeSettings::default()
+        };
+        if let Some(epoch) = &self.range_epoch {
+            settings.epoch = NaiveDate::parse_from_str(epoch, DATE_FORMAT)
+                .map_err(|_| Error::InvalidRangeEpoch(epoch.clone()))?;
+        }
+        Ok(settings)
+    }
+
+    pub fn cache<'a>(&self, client: &'a RedmineClient) -> Result<Cache<'a>> {
+        let ttl = Duration::from_secs(self.cache_ttl.unwrap_or(DEFAULT_CACHE_TTL));
+        Cache::new(client, ttl, self.page_size())
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_dir = config_dir()?;
         create_dir_all(&config_dir)?;
@@ -43,6 +278,194 @@ fn project_dirs() -> Result<ProjectDirs> {
     ProjectDirs::from("org", "readmine", "readmine").ok_or(Error::ProjectDirs)
 }
 
-fn config_dir() -> Result<PathBuf> {
+pub fn config_dir() -> Result<PathBuf> {
     Ok(project_dirs()?.config_dir().to_path_buf())
 }
+
+pub fn parse_week_start(input: &str) -> Result<Weekday> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidWeekStart(input.to_owned()))
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(project_dirs()?.cache_dir().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn week_start_setting() {
+        assert_eq!(Config::default().week_start().unwrap(), Weekday::Mon);
+        let config: Config = toml::from_str("week_start = \"sunday\"\n").unwrap();
+        assert_eq!(config.week_start().unwrap(), Weekday::Sun);
+        let config: Config = toml::from_str("week_start = \"someday\"\n").unwrap();
+        assert!(config.week_start().is_err());
+    }
+
+    #[test]
+    fn range_epoch_setting() {
+        let config: Config = toml::from_str("range_epoch = \"2019-01-01\"\n").unwrap();
+        assert_eq!(
+            config.range_settings(None).unwrap().epoch,
+            NaiveDate::from_ymd(2019, 1, 1)
+        );
+        let config: Config = toml::from_str("range_epoch = \"2019-13-01\"\n").unwrap();
+        match config.range_settings(None) {
+            Err(Error::InvalidRangeEpoch(input)) => assert_eq!(input, "2019-13-01"),
+            _ => panic!("invalid range_epoch accepted"),
+        }
+    }
+
+    #[test]
+    fn migrate_flat_config() {
+        let mut config: Config =
+            toml::from_str("url = \"https://redmine.example.com\"\napi_key = \"secret\"\n")
+                .unwrap();
+        assert!(config.migrate_flat_config());
+        assert_eq!(config.default_profile, Some("default".to_string()));
+        let profile = config.profile().unwrap();
+        assert_eq!(profile.url, "https://redmine.example.com");
+        assert_eq!(profile.api_key, Some("secret".to_string()));
+    }
+
+    #[test]
+    fn migrated_config_has_no_flat_fields() {
+        let mut config: Config = toml::from_str("url = \"https://redmine.example.com\"\n").unwrap();
+        config.migrate_flat_config();
+        let contents = toml::to_string_pretty(&config).unwrap();
+        assert!(!contents.starts_with("url"));
+        assert!(contents.contains("[profiles.default]"));
+    }
+
+    #[test]
+    fn profile_config_needs_no_migration() {
+        let mut config: Config = toml::from_str(
+            "default_profile = \"acme\"\n\n\
+             [profiles.acme]\n\
+             url = \"https://acme.example.com\"\n",
+        )
+        .unwrap();
+        assert!(!config.migrate_flat_config());
+        assert_eq!(config.profile().unwrap().url, "https://acme.example.com");
+    }
+
+    #[test]
+    fn keyring_profile_keeps_only_reference() {
+        let mut config: Config = toml::from_str(
+            "credential_store = \"keyring\"\n\n\
+             [profiles.acme]\n\
+             url = \"https://acme.example.com\"\n\
+             api_key_secret = \"acme\"\n",
+        )
+        .unwrap();
+        config.select_profile(Some("acme".to_string()));
+        assert_eq!(config.credential_store, Some(CredentialStore::Keyring));
+        let profile = config.profile().unwrap();
+        assert_eq!(profile.api_key, None);
+        assert_eq!(profile.api_key_secret, Some("acme".to_string()));
+    }
+
+    #[test]
+    fn selected_profile_overrides_default() {
+        let mut config = Config {
+            default_profile: Some("acme".to_string()),
+            ..Config::default()
+        };
+        config.select_profile(Some("globex".to_string()));
+        assert_eq!(config.profile_name(), "globex");
+    }
+
+    fn config_with_default_profile() -> Config {
+        let mut config = Config::default();
+        config.set_profile(Profile {
+            url: "https://redmine.example.com".to_string(),
+            api_key: Some("secret".to_string()),
+            api_key_secret: None,
+            secret_store: None,
+        });
+        config
+    }
+
+    #[test]
+    fn server_from_profile() {
+        let config = config_with_default_profile();
+        assert_eq!(
+            config.server().unwrap(),
+            Some((
+                "https://redmine.example.com".to_string(),
+                Some("secret".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn server_without_profile_or_overrides() {
+        assert_eq!(Config::default().server().unwrap(), None);
+    }
+
+    #[test]
+    fn server_from_overrides_only() {
+        let mut config = Config::default();
+        config.apply(Overrides {
+            profile: None,
+            url: Some("https://ci.example.com".to_string()),
+            api_key: Some("ci-key".to_string()),
+        });
+        assert_eq!(
+            config.server().unwrap(),
+            Some((
+                "https://ci.example.com".to_string(),
+                Some("ci-key".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn api_key_override_uses_profile_url() {
+        let mut config = config_with_default_profile();
+        config.apply(Overrides {
+            profile: None,
+            url: None,
+            api_key: Some("ci-key".to_string()),
+        });
+        assert_eq!(
+            config.server().unwrap(),
+            Some((
+                "https://redmine.example.com".to_string(),
+                Some("ci-key".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn url_override_does_not_use_profile_api_key() {
+        let mut config = config_with_default_profile();
+        config.apply(Overrides {
+            profile: None,
+            url: Some("https://other.example.com".to_string()),
+            api_key: None,
+        });
+        assert_eq!(
+            config.server().unwrap(),
+            Some(("https://other.example.com".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn removing_default_profile_unsets_it() {
+        let mut config = Config::default();
+        config.set_profile(Profile {
+            url: "https://redmine.example.com".to_string(),
+            api_key: None,
+            api_key_secret: None,
+            secret_store: None,
+        });
+        config.remove_profile("default").unwrap();
+        assert_eq!(config.default_profile, None);
+        assert!(config.profiles.is_empty());
+    }
+}
@@ -1,2 +1,6 @@
+/// Date format used by Redmine and accepted on the command line.
 pub const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Timestamp format used by Redmine in `created_on`/`updated_on` fields.
 pub const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+/// The largest `limit` Redmine accepts for collection requests.
+pub const MAX_PAGE_SIZE: u32 = 100;
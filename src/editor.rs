@@ -0,0 +1,35 @@
+//! Editing longer texts, e.g. issue descriptions, in the user's editor.
+
+use crate::app_error::Error;
+use crate::app_error::Result;
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Opens `$VISUAL` or `$EDITOR` on a temporary file and returns the saved text.
+pub fn edit(name: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    let path = env::temp_dir().join(format!("readmine-{}-{}.txt", name, std::process::id()));
+    fs::write(&path, "")?;
+
+    // run through the shell, so that editors given with arguments (e.g. "code --wait") work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .map_err(|error| Error::Editor(format!("cannot run {}: {}", editor, error)));
+    let text = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+
+    let status = status?;
+    if !status.success() {
+        return Err(Error::Editor(format!("{} failed ({})", editor, status)));
+    }
+    Ok(text?)
+}
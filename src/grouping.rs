@@ -0,0 +1,294 @@
+use crate::app_error::Error;
+use crate::app_error::Result;
+use chrono::{Datelike, Duration, Weekday};
+use readmine::response::TimeEntry;
+use readmine::time_range::week_start_date;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupKey {
+    Project,
+    Issue,
+    Activity,
+    Day,
+    Week,
+    Month,
+}
+
+const ALL_GROUP_KEYS: &[GroupKey] = &[
+    GroupKey::Project,
+    GroupKey::Issue,
+    GroupKey::Activity,
+    GroupKey::Day,
+    GroupKey::Week,
+    GroupKey::Month,
+];
+
+impl GroupKey {
+    pub fn parse_list(input: &str) -> Result<Vec<GroupKey>> {
+        input
+            .split(',')
+            .map(str::trim)
+            .map(GroupKey::parse)
+            .collect()
+    }
+
+    fn parse(input: &str) -> Result<GroupKey> {
+        ALL_GROUP_KEYS
+            .iter()
+            .find(|key| key.name().eq_ignore_ascii_case(input))
+            .cloned()
+            .ok_or_else(|| {
+                let names = ALL_GROUP_KEYS
+                    .iter()
+                    .map(|key| key.name().to_string())
+                    .collect();
+                Error::InvalidGroupKey(input.to_owned(), names)
+            })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupKey::Project => "project",
+            GroupKey::Issue => "issue",
+            GroupKey::Activity => "activity",
+            GroupKey::Day => "day",
+            GroupKey::Week => "week",
+            GroupKey::Month => "month",
+        }
+    }
+
+    fn value(self, entry: &TimeEntry, week_start: Weekday) -> String {
+        match self {
+            GroupKey::Project => entry.project.name.clone(),
+            GroupKey::Issue => format!("#{}", entry.issue.id),
+            GroupKey::Activity => entry.activity.name.clone(),
+            GroupKey::Day => entry.spent_on.to_string(),
+            GroupKey::Week => {
+                // weeks not starting on Monday are numbered after the ISO week
+                // of their middle day, the same way ISO weeks are after Thursday
+                let middle_day = week_start_date(entry.spent_on, week_start) + Duration::days(3);
+                let week = middle_day.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            GroupKey::Month => entry.spent_on.format("%Y-%m").to_string(),
+        }
+    }
+
+    fn compare(self, a: &TimeEntry, b: &TimeEntry) -> Ordering {
+        match self {
+            GroupKey::Project => a.project.name.cmp(&b.project.name),
+            GroupKey::Issue => a.issue.id.cmp(&b.issue.id),
+            GroupKey::Activity => a.activity.name.cmp(&b.activity.name),
+            GroupKey::Day | GroupKey::Week | GroupKey::Month => a.spent_on.cmp(&b.spent_on),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Group {
+    pub key: &'static str,
+    pub name: String,
+    pub hours: f32,
+    pub percentage: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupedTimeEntries {
+    pub hours: f32,
+    pub groups: Vec<Group>,
+}
+
+pub fn group_time_entries(
+    time_entries: &[TimeEntry],
+    keys: &[GroupKey],
+    week_start: Weekday,
+) -> GroupedTimeEntries {
+    let entries: Vec<_> = time_entries.iter().collect();
+    let hours = total_hours(&entries);
+    GroupedTimeEntries {
+        hours,
+        groups: group(&entries, keys, hours, week_start),
+    }
+}
+
+fn group(entries: &[&TimeEntry], keys: &[GroupKey], total: f32, week_start: Weekday) -> Vec<Group> {
+    let key = match keys.first() {
+        Some(key) => *key,
+        None => return Vec::new(),
+    };
+
+    let mut sorted_entries = entries.to_vec();
+    sorted_entries.sort_by(|a, b| key.compare(a, b));
+
+    let mut buckets: Vec<(String, Vec<&TimeEntry>)> = Vec::new();
+    for entry in sorted_entries {
+        let name = key.value(entry, week_start);
+        match buckets.last_mut() {
+            Some((last_name, bucket)) if *last_name == name => bucket.push(entry),
+            _ => buckets.push((name, vec![entry])),
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(name, bucket)| {
+            let hours = total_hours(&bucket);
+            Group {
+                key: key.name(),
+                name,
+                hours,
+                percentage: percentage(hours, total),
+                groups: group(&bucket, &keys[1..], total, week_start),
+            }
+        })
+        .collect()
+}
+
+fn total_hours(entries: &[&TimeEntry]) -> f32 {
+    entries.iter().fold(0.0, |sum, entry| sum + entry.hours)
+}
+
+fn percentage(hours: f32, total: f32) -> f32 {
+    if total > 0.0 {
+        hours / total * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use readmine::response::{TimeEntryActivity, TimeEntryIssue, TimeEntryProject, TimeEntryUser};
+
+    fn entry(
+        project: &str,
+        issue: i32,
+        activity: &str,
+        spent_on: NaiveDate,
+        hours: f32,
+    ) -> TimeEntry {
+        TimeEntry {
+            id: 1,
+            project: TimeEntryProject {
+                id: 1,
+                name: project.to_string(),
+            },
+            issue: TimeEntryIssue { id: issue },
+            user: TimeEntryUser {
+                id: 1,
+                name: "John Doe".to_string(),
+            },
+            activity: TimeEntryActivity {
+                id: 1,
+                name: activity.to_string(),
+            },
+            hours,
+            comments: String::new(),
+            spent_on,
+            created_on: spent_on.and_hms(12, 0, 0),
+            updated_on: spent_on.and_hms(12, 0, 0),
+        }
+    }
+
+    fn sample_entries() -> Vec<TimeEntry> {
+        vec![
+            entry("Zeta", 100, "Review", NaiveDate::from_ymd(2019, 8, 26), 2.0),
+            entry(
+                "Alpha",
+                12,
+                "Development",
+                NaiveDate::from_ymd(2019, 8, 25),
+                4.0,
+            ),
+            entry(
+                "Alpha",
+                100,
+                "Review",
+                NaiveDate::from_ymd(2019, 8, 19),
+                1.0,
+            ),
+            entry(
+                "Alpha",
+                12,
+                "Development",
+                NaiveDate::from_ymd(2019, 8, 19),
+                3.0,
+            ),
+        ]
+    }
+
+    #[test]
+    fn parse_group_keys() {
+        assert_eq!(
+            GroupKey::parse_list("project,Activity").unwrap(),
+            vec![GroupKey::Project, GroupKey::Activity]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_group_key() {
+        assert!(GroupKey::parse_list("project,year").is_err());
+    }
+
+    #[test]
+    fn group_by_project() {
+        let grouped = group_time_entries(&sample_entries(), &[GroupKey::Project], Weekday::Mon);
+        assert!((grouped.hours - 10.0).abs() < 0.001);
+        let names: Vec<_> = grouped.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Alpha", "Zeta"]);
+        assert!((grouped.groups[0].hours - 8.0).abs() < 0.001);
+        assert!((grouped.groups[0].percentage - 80.0).abs() < 0.001);
+        assert!(grouped.groups[0].groups.is_empty());
+    }
+
+    #[test]
+    fn group_by_issue_sorts_numerically() {
+        let grouped = group_time_entries(&sample_entries(), &[GroupKey::Issue], Weekday::Mon);
+        let names: Vec<_> = grouped.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["#12", "#100"]);
+    }
+
+    #[test]
+    fn group_by_iso_week() {
+        let grouped = group_time_entries(&sample_entries(), &[GroupKey::Week], Weekday::Mon);
+        let names: Vec<_> = grouped.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["2019-W34", "2019-W35"]);
+        assert!((grouped.groups[0].hours - 8.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn group_by_project_and_activity() {
+        let grouped = group_time_entries(
+            &sample_entries(),
+            &[GroupKey::Project, GroupKey::Activity],
+            Weekday::Mon,
+        );
+        let alpha = &grouped.groups[0];
+        let names: Vec<_> = alpha.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Development", "Review"]);
+        assert!((alpha.groups[0].hours - 7.0).abs() < 0.001);
+        assert!((alpha.groups[0].percentage - 70.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn group_empty_list() {
+        let grouped = group_time_entries(&[], &[GroupKey::Day], Weekday::Mon);
+        assert!(grouped.groups.is_empty());
+        assert!(grouped.hours.abs() < 0.001);
+    }
+
+    #[test]
+    fn group_by_week_starting_sunday() {
+        let grouped = group_time_entries(&sample_entries(), &[GroupKey::Week], Weekday::Sun);
+        let names: Vec<_> = grouped.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["2019-W34", "2019-W35"]);
+        assert!((grouped.groups[0].hours - 4.0).abs() < 0.001);
+        assert!((grouped.groups[1].hours - 6.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::app_error::Error;
+use crate::app_error::Result;
+use crate::TimeEntry;
+use chrono::NaiveDate;
+use readmine::constants::DATE_FORMAT;
+use readmine::time_log;
+use serde_json::{self, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const DATE_FIELD: &str = "date";
+const HOURS_FIELD: &str = "hours";
+const ISSUE_FIELD: &str = "issue";
+const ACTIVITY_FIELD: &str = "activity";
+const COMMENT_FIELD: &str = "comment";
+const REQUIRED_FIELDS: &[&str] = &[DATE_FIELD, HOURS_FIELD, ISSUE_FIELD, ACTIVITY_FIELD];
+
+pub struct ImportRow {
+    pub number: usize,
+    pub time_entry: Result<TimeEntry>,
+}
+
+pub fn read_time_entries(path: &Path) -> Result<Vec<ImportRow>> {
+    let contents = fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let records = match extension.as_deref() {
+        Some("json") => parse_json(&contents)?,
+        Some("csv") => parse_csv(&contents)?,
+        _ => {
+            return Err(Error::InvalidImportFile(format!(
+                "unknown file type of \"{}\", expected .csv or .json",
+                path.display()
+            )))
+        }
+    };
+    Ok(records
+        .into_iter()
+        .map(|(number, fields)| ImportRow {
+            number,
+            time_entry: parse_time_entry(&fields),
+        })
+        .collect())
+}
+
+fn parse_time_entry(fields: &HashMap<String, String>) -> Result<TimeEntry> {
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| Error::InvalidImportFile(format!("missing \"{}\" value", name)))
+    };
+    let spent_on = NaiveDate::parse_from_str(field(DATE_FIELD)?, DATE_FORMAT)?;
+    let hours = time_log::parse_hours(field(HOURS_FIELD)?)?;
+    let issue_id = time_log::parse_issue(field(ISSUE_FIELD)?)?;
+    let activity_name = field(ACTIVITY_FIELD)?.to_string();
+    let comments = field(COMMENT_FIELD).ok().map(str::to_string);
+    Ok(TimeEntry {
+        issue_id,
+        spent_on,
+        hours,
+        activity_name,
+        comments,
+    })
+}
+
+fn parse_json(contents: &str) -> Result<Vec<(usize, HashMap<String, String>)>> {
+    let objects: Vec<Map<String, Value>> = serde_json::from_str(contents)?;
+    Ok(objects
+        .into_iter()
+        .enumerate()
+        .map(|(index, object)| {
+            let fields = object
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    let value = match value {
+                        Value::String(value) => value,
+                        Value::Number(value) => value.to_string(),
+                        _ => return None,
+                    };
+                    Some((name.to_lowercase(), value))
+                })
+                .collect();
+            (index + 1, fields)
+        })
+        .collect())
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<(usize, HashMap<String, String>)>> {
+    let mut records = parse_csv_records(contents)?.into_iter();
+    let header: Vec<_> = match records.next() {
+        Some((_, header)) => header
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect(),
+        None => return Ok(Vec::new()),
+    };
+    if let Some(missing) = REQUIRED_FIELDS
+        .iter()
+        .find(|field| !header.iter().any(|name| name == *field))
+    {
+        return Err(Error::InvalidImportFile(format!(
+            "missing \"{}\" column in the header row",
+            missing
+        )));
+    }
+
+    Ok(records
+        .filter(|(_, record)| record.iter().any(|field| !field.trim().is_empty()))
+        .map(|(number, record)| (number, header.iter().cloned().zip(record).collect()))
+        .collect())
+}
+
+/// Splits RFC 4180 CSV text into records, returning each with its line number.
+fn parse_csv_records(contents: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+        } else {
+            match c {
+                '"' if field.is_empty() => in_quotes = true,
+                ',' => record.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push((record_line, std::mem::take(&mut record)));
+                    line += 1;
+                    record_line = line;
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(Error::InvalidImportFile(format!(
+            "unterminated quoted field starting in line {}",
+            record_line
+        )));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_simple_records() {
+        assert_eq!(
+            parse_csv_records("a,b\nc,d\n").unwrap(),
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (2, vec!["c".to_string(), "d".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_crlf_and_no_trailing_line_break() {
+        assert_eq!(
+            parse_csv_records("a,b\r\nc,").unwrap(),
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (2, vec!["c".to_string(), String::new()])
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_quoted_fields() {
+        assert_eq!(
+            parse_csv_records("\"a,b\",\"say \"\"hi\"\"\"\n").unwrap(),
+            vec![(1, vec!["a,b".to_string(), "say \"hi\"".to_string()])]
+        );
+    }
+
+    #[test]
+    fn csv_line_break_in_quoted_field() {
+        assert_eq!(
+            parse_csv_records("\"a\nb\",c\nd,e\n").unwrap(),
+            vec![
+                (1, vec!["a\nb".to_string(), "c".to_string()]),
+                (3, vec!["d".to_string(), "e".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_unterminated_quote() {
+        assert!(parse_csv_records("\"a,b\n").is_err());
+    }
+
+    #[test]
+    fn csv_time_entries() {
+        let records = parse_csv(
+            "Date,Hours,Issue,Activity,Comment\n\
+             2019-08-19,1.5h,#123,Development,\"Fixes, tests\"\n\
+             ,,,,\n\
+             2019-08-20,2,124,Review,\n",
+        )
+        .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, 2);
+        assert_eq!(records[1].0, 4);
+
+        let time_entry = parse_time_entry(&records[0].1).unwrap();
+        assert_eq!(time_entry.spent_on, NaiveDate::from_ymd(2019, 8, 19));
+        assert!((time_entry.hours - 1.5).abs() < 0.001);
+        assert_eq!(time_entry.issue_id, 123);
+        assert_eq!(time_entry.activity_name, "Development");
+        assert_eq!(time_entry.comments, Some("Fixes, tests".to_string()));
+
+        let time_entry = parse_time_entry(&records[1].1).unwrap();
+        assert_eq!(time_entry.comments, None);
+    }
+
+    #[test]
+    fn csv_missing_column() {
+        assert!(parse_csv("date,hours,activity\n2019-08-19,1,Development\n").is_err());
+    }
+
+    #[test]
+    fn csv_invalid_hours() {
+        let records = parse_csv("date,hours,issue,activity\n2019-08-19,lots,1,Dev\n").unwrap();
+        assert!(parse_time_entry(&records[0].1).is_err());
+    }
+
+    #[test]
+    fn json_time_entries() {
+        let records = parse_json(
+            r#"[{"date": "2019-08-19", "hours": 1.5, "issue": 123, "activity": "Development"}]"#,
+        )
+        .unwrap();
+        assert_eq!(records[0].0, 1);
+        let time_entry = parse_time_entry(&records[0].1).unwrap();
+        assert!((time_entry.hours - 1.5).abs() < 0.001);
+        assert_eq!(time_entry.issue_id, 123);
+    }
+}
@@ -0,0 +1,402 @@
+//! API key storage outside of the config file: in the Secret Service keyring
+//! (GNOME Keyring, KWallet etc.) through the `secret-tool` command line utility
+//! shipped with libsecret or, where no secret service is available, in a file
+//! encrypted with a passphrase.
+
+use crate::app_error::Error;
+use crate::app_error::Result;
+use crate::config::config_dir;
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rpassword::read_password_from_tty;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use toml;
+
+const SECRET_TOOL: &str = "secret-tool";
+const SERVICE_ATTRIBUTE: &str = "service";
+const SERVICE_NAME: &str = "readmine";
+const PROFILE_ATTRIBUTE: &str = "profile";
+
+const SECRETS_FILE: &str = "secrets.toml";
+const PASSPHRASE_VARIABLE: &str = "READMINE_SECRETS_PASSPHRASE";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+const KEY_ITERATIONS: usize = 100_000;
+
+/// Storage of secrets identified by a reference, e.g. the profile name.
+pub trait SecretStore {
+    fn store(&self, reference: &str, secret: &str) -> Result<()>;
+    fn lookup(&self, reference: &str) -> Result<Option<String>>;
+    fn delete(&self, reference: &str) -> Result<()>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretStoreKind {
+    SecretService,
+    EncryptedFile,
+}
+
+/// Returns the Secret Service if it can be used, and the encrypted file otherwise.
+pub fn available_store() -> SecretStoreKind {
+    if SecretTool::is_available() {
+        SecretStoreKind::SecretService
+    } else {
+        SecretStoreKind::EncryptedFile
+    }
+}
+
+pub fn open(kind: SecretStoreKind) -> Result<Box<dyn SecretStore>> {
+    Ok(match kind {
+        SecretStoreKind::SecretService => Box::new(SecretTool),
+        SecretStoreKind::EncryptedFile => Box::new(EncryptedFile::new(
+            config_dir()?.join(SECRETS_FILE),
+            Box::new(read_passphrase),
+        )),
+    })
+}
+
+/// Looks up the API key stored for the profile, failing if there is none.
+pub fn lookup_api_key(store: &dyn SecretStore, reference: &str) -> Result<String> {
+    store.lookup(reference)?.ok_or_else(|| {
+        Error::Keyring(format!(
+            "no API key stored for \"{}\", please use \"login\" command",
+            reference
+        ))
+    })
+}
+
+/// Reads the passphrase of the encrypted file from the environment
+/// or, if not set there, from the terminal.
+fn read_passphrase() -> Result<String> {
+    match env::var(PASSPHRASE_VARIABLE) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(read_password_from_tty(Some(
+            "Passphrase of stored API keys: ",
+        ))?),
+    }
+}
+
+pub struct SecretTool;
+
+impl SecretTool {
+    /// Checks that `secret-tool` is installed and there is a session bus to reach the service on.
+    fn is_available() -> bool {
+        env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+            && Command::new(SECRET_TOOL)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok()
+    }
+}
+
+impl SecretStore for SecretTool {
+    fn store(&self, reference: &str, secret: &str) -> Result<()> {
+        let mut child = Command::new(SECRET_TOOL)
+            .arg("store")
+            .arg(format!("--label=readmine API key ({})", reference))
+            .args(attributes(reference))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(keyring_error)?;
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin.write_all(secret.as_bytes())?;
+        }
+        check(child.wait_with_output()?)?;
+        Ok(())
+    }
+
+    fn lookup(&self, reference: &str) -> Result<Option<String>> {
+        let output = Command::new(SECRET_TOOL)
+            .arg("lookup")
+            .args(attributes(reference))
+            .output()
+            .map_err(keyring_error)?;
+        // secret-tool exits with an error but prints nothing if there is no such secret
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(command_error(&output));
+        }
+        let secret = String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string();
+        Ok(Some(secret).filter(|secret| !secret.is_empty()))
+    }
+
+    fn delete(&self, reference: &str) -> Result<()> {
+        let output = Command::new(SECRET_TOOL)
+            .arg("clear")
+            .args(attributes(reference))
+            .output()
+            .map_err(keyring_error)?;
+        check(output)?;
+        Ok(())
+    }
+}
+
+fn attributes(reference: &str) -> [&str; 4] {
+    [
+        SERVICE_ATTRIBUTE,
+        SERVICE_NAME,
+        PROFILE_ATTRIBUTE,
+        reference,
+    ]
+}
+
+fn check(output: Output) -> Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(command_error(&output))
+    }
+}
+
+fn command_error(output: &Output) -> Error {
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Error::Keyring(if message.is_empty() {
+        format!("{} failed ({})", SECRET_TOOL, output.status)
+    } else {
+        message
+    })
+}
+
+fn keyring_error(error: io::Error) -> Error {
+    Error::Keyring(format!("cannot run {}: {}", SECRET_TOOL, error))
+}
+
+/// Secrets encrypted with AES-256-GCM under a key derived from a passphrase,
+/// kept in a TOML file readable only by the user. The passphrase is asked for
+/// only when a secret is stored or looked up.
+pub struct EncryptedFile {
+    path: PathBuf,
+    passphrase: Box<dyn Fn() -> Result<String>>,
+}
+
+impl EncryptedFile {
+    pub fn new(path: PathBuf, passphrase: Box<dyn Fn() -> Result<String>>) -> Self {
+        EncryptedFile { path, passphrase }
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path)?;
+        write!(file, "{}", toml::to_string(secrets)?)?;
+        Ok(())
+    }
+}
+
+impl SecretStore for EncryptedFile {
+    fn store(&self, reference: &str, secret: &str) -> Result<()> {
+        let mut secrets = self.load()?;
+        let encrypted = encrypt(&(self.passphrase)()?, reference, secret)?;
+        secrets.insert(reference.to_string(), encrypted);
+        self.save(&secrets)
+    }
+
+    fn lookup(&self, reference: &str) -> Result<Option<String>> {
+        match self.load()?.get(reference) {
+            Some(encrypted) => decrypt(&(self.passphrase)()?, reference, encrypted).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&self, reference: &str) -> Result<()> {
+        let mut secrets = self.load()?;
+        if secrets.remove(reference).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encrypts the secret into hex encoded salt, nonce, cipher text and tag.
+/// The reference is authenticated too, so that secrets cannot be swapped.
+fn encrypt(passphrase: &str, reference: &str, secret: &str) -> Result<String> {
+    let mut salt = [0; SALT_LENGTH];
+    let mut nonce = [0; NONCE_LENGTH];
+    let mut tag = [0; TAG_LENGTH];
+    rand_bytes(&mut salt).map_err(crypto_error)?;
+    rand_bytes(&mut nonce).map_err(crypto_error)?;
+    let key = derive_key(passphrase, &salt)?;
+    let cipher_text = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(&nonce),
+        reference.as_bytes(),
+        secret.as_bytes(),
+        &mut tag,
+    )
+    .map_err(crypto_error)?;
+    Ok([&salt[..], &nonce, &cipher_text, &tag]
+        .iter()
+        .map(|bytes| to_hex(bytes))
+        .collect())
+}
+
+fn decrypt(passphrase: &str, reference: &str, encrypted: &str) -> Result<String> {
+    let invalid = || Error::Keyring(format!("invalid stored API key for \"{}\"", reference));
+    let bytes = from_hex(encrypted).ok_or_else(invalid)?;
+    if bytes.len() < SALT_LENGTH + NONCE_LENGTH + TAG_LENGTH {
+        return Err(invalid());
+    }
+    let (salt, rest) = bytes.split_at(SALT_LENGTH);
+    let (nonce, rest) = rest.split_at(NONCE_LENGTH);
+    let (cipher_text, tag) = rest.split_at(rest.len() - TAG_LENGTH);
+    let key = derive_key(passphrase, salt)?;
+    let secret = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(nonce),
+        reference.as_bytes(),
+        cipher_text,
+        tag,
+    )
+    .map_err(|_| {
+        Error::Keyring(format!(
+            "cannot decrypt API key for \"{}\", wrong passphrase?",
+            reference
+        ))
+    })?;
+    String::from_utf8(secret).map_err(|_| invalid())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LENGTH]> {
+    let mut key = [0; KEY_LENGTH];
+    pbkdf2_hmac(
+        passphrase.as_bytes(),
+        salt,
+        KEY_ITERATIONS,
+        MessageDigest::sha256(),
+        &mut key,
+    )
+    .map_err(crypto_error)?;
+    Ok(key)
+}
+
+fn crypto_error(error: openssl::error::ErrorStack) -> Error {
+    Error::Keyring(format!("encryption failed: {}", error))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((hex_digit(*high)? << 4) | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encrypted file in its own temporary directory, which is removed when dropped.
+    struct TestFile {
+        dir: PathBuf,
+        store: EncryptedFile,
+    }
+
+    impl TestFile {
+        fn new(name: &str) -> Self {
+            let dir =
+                env::temp_dir().join(format!("readmine-test-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            let store = EncryptedFile::new(
+                dir.join(SECRETS_FILE),
+                Box::new(|| Ok("passphrase".to_string())),
+            );
+            TestFile { dir, store }
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn encrypted_file_round_trip() {
+        let file = TestFile::new("round-trip");
+        file.store.store("work", "secret-key").unwrap();
+        file.store.store("home", "other-key").unwrap();
+        assert_eq!(lookup_api_key(&file.store, "work").unwrap(), "secret-key");
+        assert_eq!(lookup_api_key(&file.store, "home").unwrap(), "other-key");
+        let contents = fs::read_to_string(&file.store.path).unwrap();
+        assert!(!contents.contains("secret-key"));
+    }
+
+    #[test]
+    fn encrypted_file_missing_secret() {
+        let file = TestFile::new("missing");
+        assert_eq!(file.store.lookup("nothing").unwrap(), None);
+        assert!(lookup_api_key(&file.store, "nothing").is_err());
+    }
+
+    #[test]
+    fn encrypted_file_delete() {
+        let file = TestFile::new("delete");
+        file.store.store("work", "secret-key").unwrap();
+        file.store.delete("work").unwrap();
+        file.store.delete("work").unwrap();
+        assert_eq!(file.store.lookup("work").unwrap(), None);
+    }
+
+    #[test]
+    fn encrypted_file_wrong_passphrase() {
+        let file = TestFile::new("wrong");
+        file.store.store("work", "secret-key").unwrap();
+        let store = EncryptedFile::new(
+            file.store.path.clone(),
+            Box::new(|| Ok("other".to_string())),
+        );
+        assert!(store.lookup("work").is_err());
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("000fff"), Some(vec![0, 15, 255]));
+        assert_eq!(from_hex("0g"), None);
+        assert_eq!(from_hex("abc"), None);
+    }
+}
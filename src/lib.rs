@@ -0,0 +1,35 @@
+//! Client library for the Redmine REST API.
+//!
+//! This crate powers the `readmine` command line tool, but the API client,
+//! the request/response models and the parsers for time ranges and time log
+//! entries can be used on their own:
+//!
+//! ```no_run
+//! use readmine::{Credentials, RedmineClient, TimeRange};
+//!
+//! # async fn run() -> readmine::Result<()> {
+//! let client = RedmineClient::new(
+//!     "https://redmine.example.com",
+//!     Credentials::ApiKey("secret".to_string()),
+//! )?;
+//! let range = TimeRange::parse("month-1")?;
+//! for entry in client.time_entries(&range, 100).await? {
+//!     println!("{} {}h {}", entry.spent_on, entry.hours, entry.comments);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod constants;
+pub mod error;
+pub mod request;
+pub mod response;
+pub mod result;
+mod serialization_formats;
+pub mod time_log;
+pub mod time_range;
+
+pub use crate::error::Error;
+pub use crate::request::{Credentials, RedmineClient};
+pub use crate::result::Result;
+pub use crate::time_range::TimeRange;
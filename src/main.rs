@@ -1,28 +1,85 @@
+mod app_error;
+mod cache;
 mod cli;
 mod config;
-mod constants;
-mod error;
-mod request;
-mod response;
-mod result;
-mod serialization_formats;
-mod time_log;
-mod time_range;
-
-use crate::config::Config;
-use crate::constants::DATE_FORMAT;
-use crate::error::Error;
-use crate::result::Result;
-use crate::time_range::TimeRange;
+mod editor;
+mod grouping;
+mod import;
+mod keyring;
+mod output;
+mod timer;
+
+use crate::app_error::{Error, Result};
+use crate::cache::{Cache, Named};
+use crate::config::{Config, Overrides};
+use crate::grouping::GroupKey;
+use crate::output::{
+    Column, DetailNames, IssueColumn, OutputFormat, TimeEntryColumn, DEFAULT_ISSUE_COLUMNS,
+    DEFAULT_TIME_ENTRY_COLUMNS,
+};
+use crate::timer::Timer;
 use chrono::prelude::*;
+use clap::ArgMatches;
+use readmine::constants::DATE_FORMAT;
+use readmine::request::{IssueFilter, PasswordSource};
+use readmine::time_range::TimeRange;
+use readmine::RedmineClient;
+use readmine::{request, response, time_log};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
 use term;
 
 enum Command {
-    Login { url: String, email: Option<String> },
+    Login {
+        url: String,
+        email: Option<String>,
+        api_key: Option<String>,
+        password_source: PasswordSource,
+    },
     Logout,
+    ProfileList,
+    ProfileUse(String),
+    ProfileRemove(String),
     User,
-    Time(TimeRange),
+    Time {
+        range: String,
+        week_start: Option<Weekday>,
+        columns: Vec<TimeEntryColumn>,
+        format: OutputFormat,
+        group_by: Vec<GroupKey>,
+    },
     TimeAdd(TimeEntry),
+    TimeEdit(i32, TimeEntryChanges),
+    TimeDelete {
+        ids: Vec<i32>,
+        confirmed: bool,
+    },
+    TimeImport {
+        path: PathBuf,
+        dry_run: bool,
+    },
+    TimeStart(Timer),
+    TimeStatus,
+    TimeStop {
+        confirmed: bool,
+    },
+    TimeCancel,
+    Issues {
+        filter: IssueFilter,
+        offset: u32,
+        limit: u32,
+        columns: Vec<IssueColumn>,
+        format: OutputFormat,
+    },
+    IssueShow(i32),
+    IssueNew(NewIssue),
+    IssueUpdate {
+        id: i32,
+        changes: IssueChanges,
+        time_entry: Option<TimeEntry>,
+    },
+    CacheRefresh,
+    CacheClear,
 }
 
 pub struct TimeEntry {
@@ -38,22 +95,191 @@ impl TimeEntry {
         self,
         activities: &[response::TimeEntryActivity],
     ) -> Result<request::TimeEntry> {
-        activities
-            .iter()
-            .find(|activity| self.activity_name == activity.name)
-            .ok_or_else(|| {
-                error::Error::InvalidActivityName(self.activity_name.clone(), activities.to_owned())
-            })
-            .map(|activity| request::TimeEntry {
-                issue_id: self.issue_id,
-                spent_on: self.spent_on,
-                hours: self.hours,
-                comments: self.comments,
-                activity_id: activity.id,
-            })
+        find_activity(&self.activity_name, activities).map(|activity| request::TimeEntry {
+            issue_id: self.issue_id,
+            spent_on: self.spent_on,
+            hours: self.hours,
+            comments: self.comments,
+            activity_id: activity.id,
+        })
+    }
+}
+
+pub struct TimeEntryChanges {
+    pub issue_id: Option<i32>,
+    pub spent_on: Option<NaiveDate>,
+    pub hours: Option<f32>,
+    pub activity_name: Option<String>,
+    pub comments: Option<String>,
+}
+
+impl TimeEntryChanges {
+    fn into_request(
+        self,
+        activities: &[response::TimeEntryActivity],
+    ) -> Result<request::TimeEntryUpdate> {
+        let activity_id = match &self.activity_name {
+            Some(activity_name) => Some(find_activity(activity_name, activities)?.id),
+            None => None,
+        };
+        Ok(request::TimeEntryUpdate {
+            issue_id: self.issue_id,
+            spent_on: self.spent_on,
+            hours: self.hours,
+            activity_id,
+            comments: self.comments,
+        })
+    }
+}
+
+pub struct NewIssue {
+    pub project: String,
+    pub tracker_name: String,
+    pub subject: String,
+    pub description: Option<String>,
+    pub status_name: Option<String>,
+    pub priority_name: Option<String>,
+    pub assignee: Option<String>,
+    pub parent_issue_id: Option<i32>,
+    pub start_date: Option<NaiveDate>,
+    pub due_date: Option<NaiveDate>,
+    pub estimated_hours: Option<f32>,
+}
+
+impl NewIssue {
+    fn into_request(
+        self,
+        trackers: &[response::Reference],
+        statuses: &[response::Reference],
+        priorities: &[response::Reference],
+        assigned_to_id: Option<i32>,
+    ) -> Result<request::Issue> {
+        let tracker_id = find_by_name("tracker", &self.tracker_name, trackers)?.id;
+        let status_id = match &self.status_name {
+            Some(status_name) => Some(find_by_name("status", status_name, statuses)?.id),
+            None => None,
+        };
+        let priority_id = match &self.priority_name {
+            Some(priority_name) => Some(find_by_name("priority", priority_name, priorities)?.id),
+            None => None,
+        };
+        Ok(request::Issue {
+            project_id: self.project,
+            tracker_id,
+            subject: self.subject,
+            description: self.description,
+            status_id,
+            priority_id,
+            assigned_to_id,
+            parent_issue_id: self.parent_issue_id,
+            start_date: self.start_date,
+            due_date: self.due_date,
+            estimated_hours: self.estimated_hours,
+        })
+    }
+}
+
+pub struct IssueChanges {
+    pub status_name: Option<String>,
+    pub assignee: Option<String>,
+    pub done_ratio: Option<u32>,
+    pub version_id: Option<i32>,
+    pub notes: Option<String>,
+}
+
+impl IssueChanges {
+    fn into_request(
+        self,
+        statuses: &[response::Reference],
+        assigned_to_id: Option<i32>,
+    ) -> Result<request::IssueUpdate> {
+        let status_id = match &self.status_name {
+            Some(status_name) => Some(find_by_name("status", status_name, statuses)?.id),
+            None => None,
+        };
+        Ok(request::IssueUpdate {
+            status_id,
+            assigned_to_id,
+            done_ratio: self.done_ratio,
+            fixed_version_id: self.version_id,
+            notes: self.notes,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.status_name.is_none()
+            && self.assignee.is_none()
+            && self.done_ratio.is_none()
+            && self.version_id.is_none()
+            && self.notes.is_none()
+    }
+}
+
+/// Builds the requests of the `issue update` command, failing before
+/// any of them is sent if a name cannot be resolved.
+fn prepare_issue_update(
+    changes: IssueChanges,
+    time_entry: Option<TimeEntry>,
+    activities: &[response::TimeEntryActivity],
+    statuses: &[response::Reference],
+    assigned_to_id: Option<i32>,
+) -> Result<(Option<request::TimeEntry>, Option<request::IssueUpdate>)> {
+    let time_entry = time_entry
+        .map(|time_entry| time_entry.into_request(activities))
+        .transpose()?;
+    let update = if changes.is_empty() {
+        None
+    } else {
+        Some(changes.into_request(statuses, assigned_to_id)?)
+    };
+    Ok((time_entry, update))
+}
+
+/// Finds an item by name, ignoring case like [`find_activity`].
+fn find_by_name<'a>(
+    kind: &'static str,
+    name: &str,
+    items: &'a [response::Reference],
+) -> Result<&'a response::Reference> {
+    items
+        .iter()
+        .find(|item| item.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let names = items.iter().map(|item| item.name.clone()).collect();
+            Error::InvalidName(kind, name.to_owned(), names)
+        })
+}
+
+/// Resolves a project given by name, identifier or id to its identifier.
+/// Unknown projects are passed on as given.
+async fn resolve_project(cache: &Cache<'_>, project: &str) -> Result<String> {
+    let projects = cache.projects(&[project]).await?;
+    Ok(projects
+        .iter()
+        .find(|item| item.matches(project))
+        .map_or_else(|| project.to_string(), |item| item.identifier.clone()))
+}
+
+/// Resolves the user id given as a number or `me`.
+async fn resolve_assignee(client: &RedmineClient, assignee: &str) -> Result<i32> {
+    if assignee == "me" {
+        Ok(client.current_user().await?.id)
+    } else {
+        parse_number(assignee)
     }
 }
 
+/// Finds an activity by name, ignoring case like [`find_by_name`].
+fn find_activity<'a>(
+    activity_name: &str,
+    activities: &'a [response::TimeEntryActivity],
+) -> Result<&'a response::TimeEntryActivity> {
+    activities
+        .iter()
+        .find(|activity| activity.name.eq_ignore_ascii_case(activity_name))
+        .ok_or_else(|| Error::InvalidActivityName(activity_name.to_owned(), activities.to_owned()))
+}
+
 #[tokio::main]
 async fn main() {
     if let Err(error) = just_run().await {
@@ -64,16 +290,49 @@ async fn main() {
 
 async fn just_run() -> Result<()> {
     let matches = cli::build_cli().get_matches();
+    let overrides = Overrides {
+        profile: global_value_of(&matches, "profile"),
+        url: global_value_of(&matches, "server_url"),
+        api_key: global_value_of(&matches, "api_key"),
+    };
 
+    let app_matches = &matches;
     let command = if let Some(matches) = matches.subcommand_matches("login") {
         let url = matches
             .value_of("url")
             .expect("missing \"url\" parameter in \"server\" command")
             .to_string();
         let email = matches.value_of("name").map(str::to_string);
-        Command::Login { url, email }
+        let api_key = login_api_key(app_matches, matches).unwrap_or_else(|error| error.exit());
+        let password_source = if matches.is_present("password_stdin") {
+            PasswordSource::Stdin
+        } else {
+            PasswordSource::Terminal
+        };
+        Command::Login {
+            url,
+            email,
+            api_key,
+            password_source,
+        }
     } else if matches.subcommand_matches("logout").is_some() {
         Command::Logout
+    } else if let Some(matches) = matches.subcommand_matches("profile") {
+        if matches.subcommand_matches("list").is_some() {
+            Command::ProfileList
+        } else if let Some(matches) = matches.subcommand_matches("use") {
+            let name = matches
+                .value_of("name")
+                .expect("missing \"name\" parameter in \"profile use\" command");
+            Command::ProfileUse(name.to_string())
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let name = matches
+                .value_of("name")
+                .expect("missing \"name\" parameter in \"profile remove\" command");
+            Command::ProfileRemove(name.to_string())
+        } else {
+            unreachable!();
+        }
     } else if matches.subcommand_matches("user").is_some() {
         Command::User
     } else if let Some(matches) = matches.subcommand_matches("time") {
@@ -104,38 +363,361 @@ async fn just_run() -> Result<()> {
                 activity_name,
                 comments,
             })
+        } else if let Some(matches) = matches.subcommand_matches("edit") {
+            let id = parse_time_entry_id(
+                matches
+                    .value_of("id")
+                    .expect("missing \"id\" parameter in \"time edit\" command"),
+            )?;
+            let spent_on = matches
+                .value_of("date")
+                .map(|date| NaiveDate::parse_from_str(date, DATE_FORMAT))
+                .transpose()?;
+            let hours = matches
+                .value_of("hours")
+                .map(time_log::parse_hours)
+                .transpose()?;
+            let issue_id = matches
+                .value_of("issue_id")
+                .map(time_log::parse_issue)
+                .transpose()?;
+            let activity_name = matches.value_of("activity").map(str::to_string);
+            let comments = matches.value_of("comment").map(str::to_string);
+            Command::TimeEdit(
+                id,
+                TimeEntryChanges {
+                    issue_id,
+                    spent_on,
+                    hours,
+                    activity_name,
+                    comments,
+                },
+            )
+        } else if let Some(matches) = matches.subcommand_matches("delete") {
+            let ids = matches
+                .values_of("id")
+                .expect("missing \"id\" parameter in \"time delete\" command")
+                .map(parse_time_entry_id)
+                .collect::<Result<_>>()?;
+            let confirmed = matches.is_present("yes");
+            Command::TimeDelete { ids, confirmed }
+        } else if let Some(matches) = matches.subcommand_matches("import") {
+            let path = matches
+                .value_of("file")
+                .expect("missing \"file\" parameter in \"time import\" command")
+                .into();
+            let dry_run = matches.is_present("dry_run");
+            Command::TimeImport { path, dry_run }
+        } else if let Some(matches) = matches.subcommand_matches("start") {
+            let issue_id = time_log::parse_issue(
+                matches
+                    .value_of("issue_id")
+                    .expect("missing \"issue_id\" parameter in \"time start\" command"),
+            )?;
+            let activity_name = matches
+                .value_of("activity")
+                .expect("missing \"activity\" parameter in \"time start\" command")
+                .to_string();
+            let comments = matches.value_of("comment").map(str::to_string);
+            Command::TimeStart(Timer::start(issue_id, activity_name, comments))
+        } else if matches.subcommand_matches("status").is_some() {
+            Command::TimeStatus
+        } else if matches.subcommand_matches("stop").is_some() {
+            Command::TimeStop {
+                confirmed: matches.is_present("yes"),
+            }
+        } else if matches.subcommand_matches("cancel").is_some() {
+            Command::TimeCancel
         } else {
-            Command::Time(TimeRange::parse(
+            let range = matches
+                .value_of("range")
+                .expect("missing \"range\" parameter in \"time\" command")
+                .to_string();
+            let week_start = matches
+                .value_of("week_start")
+                .map(config::parse_week_start)
+                .transpose()?;
+            let columns = match matches.value_of("columns") {
+                Some(columns) => TimeEntryColumn::parse_list(columns)?,
+                None => DEFAULT_TIME_ENTRY_COLUMNS.to_vec(),
+            };
+            let format = OutputFormat::parse(
                 matches
-                    .value_of("range")
-                    .expect("missing \"range\" parameter in \"time\" command"),
-            )?)
+                    .value_of("format")
+                    .expect("missing \"format\" parameter in \"time\" command"),
+            )?;
+            let group_by = match matches.value_of("group_by") {
+                Some(group_by) => GroupKey::parse_list(group_by)?,
+                None => Vec::new(),
+            };
+            Command::Time {
+                range,
+                week_start,
+                columns,
+                format,
+                group_by,
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("issues") {
+        let filter = IssueFilter {
+            project: matches.value_of("project").map(str::to_string),
+            assignee: matches.value_of("assignee").map(str::to_string),
+            status: matches.value_of("status").map(str::to_string),
+            tracker_id: parse_optional_number(matches.value_of("tracker"))?,
+            version_id: parse_optional_number(matches.value_of("version"))?,
+            priority_id: parse_optional_number(matches.value_of("priority"))?,
+            subject: matches.value_of("subject").map(str::to_string),
+            sort: matches.value_of("sort").map(str::to_string),
+        };
+        let limit = parse_number(
+            matches
+                .value_of("limit")
+                .expect("missing \"limit\" parameter in \"issues\" command"),
+        )?;
+        let offset = parse_number(
+            matches
+                .value_of("offset")
+                .expect("missing \"offset\" parameter in \"issues\" command"),
+        )?;
+        let columns = match matches.value_of("columns") {
+            Some(columns) => IssueColumn::parse_list(columns)?,
+            None => DEFAULT_ISSUE_COLUMNS.to_vec(),
+        };
+        let format = OutputFormat::parse(
+            matches
+                .value_of("format")
+                .expect("missing \"format\" parameter in \"issues\" command"),
+        )?;
+        Command::Issues {
+            filter,
+            offset,
+            limit,
+            columns,
+            format,
+        }
+    } else if let Some(matches) = matches.subcommand_matches("issue") {
+        if let Some(matches) = matches.subcommand_matches("show") {
+            let issue_id = time_log::parse_issue(
+                matches
+                    .value_of("issue_id")
+                    .expect("missing \"issue_id\" parameter in \"issue show\" command"),
+            )?;
+            Command::IssueShow(issue_id)
+        } else if let Some(matches) = matches.subcommand_matches("new") {
+            let description = if matches.is_present("edit") {
+                Some(editor::edit("issue")?)
+            } else {
+                match matches.value_of("description") {
+                    Some("-") => {
+                        let mut description = String::new();
+                        io::stdin().read_to_string(&mut description)?;
+                        Some(description)
+                    }
+                    description => description.map(str::to_string),
+                }
+            };
+            let date = |name| {
+                matches
+                    .value_of(name)
+                    .map(|date| NaiveDate::parse_from_str(date, DATE_FORMAT))
+                    .transpose()
+            };
+            Command::IssueNew(NewIssue {
+                project: matches
+                    .value_of("project")
+                    .expect("missing \"project\" parameter in \"issue new\" command")
+                    .to_string(),
+                tracker_name: matches
+                    .value_of("tracker")
+                    .expect("missing \"tracker\" parameter in \"issue new\" command")
+                    .to_string(),
+                subject: matches
+                    .value_of("subject")
+                    .expect("missing \"subject\" parameter in \"issue new\" command")
+                    .to_string(),
+                description: description.filter(|description| !description.trim().is_empty()),
+                status_name: matches.value_of("status").map(str::to_string),
+                priority_name: matches.value_of("priority").map(str::to_string),
+                assignee: matches.value_of("assignee").map(str::to_string),
+                parent_issue_id: matches
+                    .value_of("parent")
+                    .map(time_log::parse_issue)
+                    .transpose()?,
+                start_date: date("start")?,
+                due_date: date("due")?,
+                estimated_hours: estimated_hours(matches)?,
+            })
+        } else if let Some(matches) = matches.subcommand_matches("update") {
+            let id = time_log::parse_issue(
+                matches
+                    .value_of("issue_id")
+                    .expect("missing \"issue_id\" parameter in \"issue update\" command"),
+            )?;
+            let changes = IssueChanges {
+                status_name: matches.value_of("status").map(str::to_string),
+                assignee: matches.value_of("assignee").map(str::to_string),
+                done_ratio: parse_optional_number(matches.value_of("done"))?,
+                version_id: parse_optional_number(matches.value_of("version"))?,
+                notes: matches.value_of("note").map(str::to_string),
+            };
+            let time_entry = match matches.value_of("log") {
+                Some(hours) => Some(TimeEntry {
+                    issue_id: id,
+                    spent_on: Local::today().naive_local(),
+                    hours: time_log::parse_hours(hours)?,
+                    activity_name: matches
+                        .value_of("activity")
+                        .expect("missing \"activity\" parameter in \"issue update\" command")
+                        .to_string(),
+                    comments: None,
+                }),
+                None => None,
+            };
+            Command::IssueUpdate {
+                id,
+                changes,
+                time_entry,
+            }
+        } else {
+            unreachable!();
+        }
+    } else if let Some(matches) = matches.subcommand_matches("cache") {
+        if matches.subcommand_matches("refresh").is_some() {
+            Command::CacheRefresh
+        } else if matches.subcommand_matches("clear").is_some() {
+            Command::CacheClear
+        } else {
+            unreachable!();
         }
     } else {
         unreachable!();
     };
 
-    run_command(command).await
+    run_command(command, overrides).await
+}
+
+/// Returns the value of a global argument, which clap only stores
+/// in the matches of the subcommand it was given after.
+fn global_value_of(matches: &ArgMatches, name: &str) -> Option<String> {
+    let mut value = matches.value_of(name).map(str::to_string);
+    let mut matches = matches;
+    while let (_, Some(subcommand_matches)) = matches.subcommand() {
+        if let Some(subcommand_value) = subcommand_matches.value_of(name) {
+            value = Some(subcommand_value.to_string());
+        }
+        matches = subcommand_matches;
+    }
+    value
+}
+
+/// Returns how many times a global argument was given on the command line,
+/// not counting a value taken from the environment.
+fn global_occurrences_of(matches: &ArgMatches, name: &str) -> u64 {
+    let mut occurrences = matches.occurrences_of(name);
+    let mut matches = matches;
+    while let (_, Some(subcommand_matches)) = matches.subcommand() {
+        occurrences += subcommand_matches.occurrences_of(name);
+        matches = subcommand_matches;
+    }
+    occurrences
+}
+
+/// Returns the API key to log in with. `--password-stdin` conflicts only with
+/// `--api-key` given explicitly, so that a key exported in READMINE_API_KEY
+/// does not take the place of the password.
+fn login_api_key(app_matches: &ArgMatches, matches: &ArgMatches) -> clap::Result<Option<String>> {
+    if !matches.is_present("password_stdin") {
+        Ok(global_value_of(app_matches, "api_key"))
+    } else if global_occurrences_of(app_matches, "api_key") > 0 {
+        Err(clap::Error::with_description(
+            "The argument '--password-stdin' cannot be used with '--api-key'",
+            clap::ErrorKind::ArgumentConflict,
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Estimated time of an issue, which unlike a time entry may be more than a day.
+fn estimated_hours(matches: &ArgMatches) -> Result<Option<f32>> {
+    Ok(matches
+        .value_of("estimated")
+        .map(time_log::parse_duration)
+        .transpose()?)
+}
+
+fn parse_number<T: std::str::FromStr>(input: &str) -> Result<T> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidNumber(input.to_owned()))
+}
+
+fn parse_optional_number<T: std::str::FromStr>(input: Option<&str>) -> Result<Option<T>> {
+    input.map(parse_number).transpose()
+}
+
+fn parse_time_entry_id(input: &str) -> Result<i32> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidTimeEntryId(input.to_owned()))
 }
 
-async fn run_command(command: Command) -> Result<()> {
+async fn run_command(command: Command, overrides: Overrides) -> Result<()> {
     let mut config = Config::load()?;
+    config.apply(overrides);
 
     match command {
-        Command::Login { url, email } => {
-            let user = request::login(&url, email).await?;
-            config.url = Some(url);
-            config.api_key = Some(user.api_key);
+        Command::Login {
+            url,
+            email,
+            api_key,
+            password_source,
+        } => {
+            let user = if let Some(api_key) = api_key {
+                request::login_with_api_key(&url, api_key).await?
+            } else {
+                request::login(&url, email, password_source).await?
+            };
+            let profile = config.new_profile(url, user.api_key)?;
+            config.set_profile(profile);
             config.save()
         }
         Command::Logout => {
-            config.url = None;
-            config.api_key = None;
+            // without a profile there is nothing to log out of
+            if config.profile().is_some() {
+                let name = config.profile_name().to_string();
+                let profile = config.remove_profile(&name)?;
+                config.save()?;
+                forget_api_key(&name, &profile);
+            }
+            Ok(())
+        }
+        Command::ProfileList => {
+            for (name, profile) in &config.profiles {
+                let marker = if config.default_profile.as_ref() == Some(name) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}   {}", marker, name, profile.url);
+            }
+            Ok(())
+        }
+        Command::ProfileUse(name) => {
+            config.use_profile(&name)?;
             config.save()
         }
+        Command::ProfileRemove(name) => {
+            let profile = config.remove_profile(&name)?;
+            config.save()?;
+            forget_api_key(&name, &profile);
+            Ok(())
+        }
         Command::User => {
-            if let Some(url) = &config.url {
-                let user = request::user(url, &config.api_key).await?;
+            if let Some(client) = config.client()? {
+                let user = client.current_user().await?;
                 println!("id: {}\nlogin: {}\nfirst name: {}\nlast name: {}\nmail: {}\ncreated on: {}\nlast login on: {}\napi key: {}",
                     user.id, user.login, user.firstname, user.lastname, user.mail, user.created_on, user.last_login_on, user.api_key)
             } else {
@@ -143,67 +725,611 @@ async fn run_command(command: Command) -> Result<()> {
             };
             Ok(())
         }
-        Command::Time(range) => {
-            if let Some(url) = &config.url {
-                let mut t = term::stdout().ok_or(Error::CannotOpenTerminal)?;
-                let time_entries = request::time(url, &config.api_key, &range).await?;
-                let total = time_entries
-                    .iter()
-                    .fold(0.0, |sum, entry| sum + entry.hours);
-                let max_project_title_len = time_entries
-                    .iter()
-                    .map(|entry| entry.project.name.len())
-                    .max()
-                    .unwrap_or(0);
-                let max_activity_len = time_entries
-                    .iter()
-                    .map(|entry| entry.activity.name.len())
-                    .max()
-                    .unwrap_or(0);
-                for entry in time_entries {
-                    t.fg(term::color::WHITE)?;
-                    write!(t, "{}   ", entry.spent_on)?;
-                    t.attr(term::Attr::Bold)?;
-                    t.fg(term::color::WHITE)?;
-                    write!(t, "{}", entry.hours)?;
-                    t.reset()?;
-                    write!(
-                        t,
-                        "h   #{} {:width$}   ",
-                        entry.issue.id,
-                        entry.project.name,
-                        width = max_project_title_len
-                    )?;
-                    write!(
-                        t,
-                        "{:width$}    ",
-                        entry.activity.name,
-                        width = max_activity_len
-                    )?;
-                    t.fg(term::color::YELLOW)?;
-                    writeln!(t, "{}", entry.comments)?;
-                }
-                t.fg(term::color::WHITE)?;
-                write!(t, "Total time: ")?;
-                t.attr(term::Attr::Bold)?;
-                t.fg(term::color::WHITE)?;
-                write!(t, "{}", total)?;
-                t.reset()?;
-                writeln!(t, "h")?;
+        Command::Time {
+            range,
+            week_start,
+            columns,
+            format,
+            group_by,
+        } => {
+            // the range is parsed only here, as its settings may come from the config
+            let settings = config.range_settings(week_start)?;
+            let range = TimeRange::parse_with_settings(&range, &settings)?;
+            if let Some(client) = config.client()? {
+                let time_entries = client.time_entries(&range, config.page_size()).await?;
+                if !group_by.is_empty() {
+                    let grouped =
+                        grouping::group_time_entries(&time_entries, &group_by, settings.week_start);
+                    match format {
+                        OutputFormat::Table => {
+                            let mut t = term::stdout().ok_or(Error::CannotOpenTerminal)?;
+                            output::print_groups(&mut *t, &grouped)?;
+                        }
+                        OutputFormat::Json => output::write_json(&mut io::stdout(), &grouped)?,
+                        OutputFormat::Csv | OutputFormat::Tsv => output::write_groups_delimited(
+                            &mut io::stdout(),
+                            &grouped,
+                            &group_by,
+                            format,
+                        )?,
+                    }
+                } else {
+                    match format {
+                        OutputFormat::Table => {
+                            let mut t = term::stdout().ok_or(Error::CannotOpenTerminal)?;
+                            output::print_time_entries(&mut *t, &time_entries, &columns)?;
+                        }
+                        OutputFormat::Json => output::write_json(&mut io::stdout(), &time_entries)?,
+                        OutputFormat::Csv | OutputFormat::Tsv => output::write_delimited(
+                            &mut io::stdout(),
+                            &time_entries,
+                            &columns,
+                            format,
+                        )?,
+                    }
+                }
             } else {
                 println!("Server details not set. Please use \"login\" command first.");
             };
             Ok(())
         }
         Command::TimeAdd(time_entry) => {
-            if let Some(url) = &config.url {
-                let activities = request::activities(url, &config.api_key).await?;
+            if let Some(client) = config.client()? {
+                let activities = config
+                    .cache(&client)?
+                    .activities(&[&time_entry.activity_name])
+                    .await?;
                 let time_entry = time_entry.into_request(&activities)?;
-                request::time_add(url, &config.api_key, time_entry).await
+                Ok(client.create_time_entry(time_entry).await?)
             } else {
                 println!("Server details not set. Please use \"login\" command first.");
                 Ok(())
             }
         }
+        Command::TimeEdit(id, changes) => {
+            if let Some(client) = config.client()? {
+                let activities = match &changes.activity_name {
+                    Some(activity_name) => {
+                        config.cache(&client)?.activities(&[activity_name]).await?
+                    }
+                    None => Vec::new(),
+                };
+                let update = changes.into_request(&activities)?;
+                let before = client.time_entry(id).await?;
+                client.update_time_entry(id, update).await?;
+                let after = client.time_entry(id).await?;
+                print_time_entry("Before", &before);
+                print_time_entry("After", &after);
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            }
+            Ok(())
+        }
+        Command::TimeDelete { ids, confirmed } => {
+            if let Some(client) = config.client()? {
+                let mut failed_ids = Vec::new();
+                let mut found_ids = Vec::new();
+                for id in ids {
+                    match client.time_entry(id).await {
+                        Ok(entry) => {
+                            print_time_entry(&format!("#{}", id), &entry);
+                            found_ids.push(id);
+                        }
+                        Err(error) => {
+                            eprintln!("#{}: {}", id, error);
+                            failed_ids.push(id);
+                        }
+                    }
+                }
+
+                if !found_ids.is_empty()
+                    && (confirmed || confirm("Delete the time entries listed above?")?)
+                {
+                    for id in found_ids {
+                        match client.delete_time_entry(id).await {
+                            Ok(()) => println!("#{}: deleted", id),
+                            Err(error) => {
+                                eprintln!("#{}: {}", id, error);
+                                failed_ids.push(id);
+                            }
+                        }
+                    }
+                }
+
+                if !failed_ids.is_empty() {
+                    return Err(Error::TimeEntriesNotDeleted(failed_ids));
+                }
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            }
+            Ok(())
+        }
+        Command::TimeImport { path, dry_run } => {
+            if let Some(client) = config.client()? {
+                let rows = import::read_time_entries(&path)?;
+                let activity_names: Vec<_> = rows
+                    .iter()
+                    .filter_map(|row| row.time_entry.as_ref().ok())
+                    .map(|time_entry| time_entry.activity_name.as_str())
+                    .collect();
+                let activities = config.cache(&client)?.activities(&activity_names).await?;
+                let mut time_entries = Vec::new();
+                let mut rejected = 0;
+                for row in rows {
+                    match row
+                        .time_entry
+                        .and_then(|time_entry| time_entry.into_request(&activities))
+                    {
+                        Ok(time_entry) => time_entries.push((row.number, time_entry)),
+                        Err(error) => {
+                            eprintln!("row {}: rejected: {}", row.number, error);
+                            rejected += 1;
+                        }
+                    }
+                }
+                if rejected > 0 {
+                    return Err(Error::ImportRejected(rejected));
+                }
+
+                for (number, time_entry) in time_entries {
+                    let description = format!(
+                        "{}   {}h   #{}",
+                        time_entry.spent_on, time_entry.hours, time_entry.issue_id
+                    );
+                    if dry_run {
+                        println!("row {}: valid ({})", number, description);
+                        continue;
+                    }
+                    match client.create_time_entry(time_entry).await {
+                        Ok(()) => println!("row {}: created ({})", number, description),
+                        Err(error) => {
+                            eprintln!("row {}: rejected: {}", number, error);
+                            rejected += 1;
+                        }
+                    }
+                }
+                if rejected > 0 {
+                    return Err(Error::ImportRejected(rejected));
+                }
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            }
+            Ok(())
+        }
+        Command::TimeStart(timer) => {
+            if Timer::load()?.is_some() {
+                return Err(Error::TimerAlreadyRunning);
+            }
+            if let Some(client) = config.client()? {
+                let activities = config
+                    .cache(&client)?
+                    .activities(&[&timer.activity_name])
+                    .await?;
+                find_activity(&timer.activity_name, &activities)?;
+                timer.save()?;
+                print_timer(&timer)?;
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            }
+            Ok(())
+        }
+        Command::TimeStatus => {
+            match Timer::load()? {
+                Some(timer) => print_timer(&timer)?,
+                None => println!("Timer is not running."),
+            }
+            Ok(())
+        }
+        Command::TimeStop { confirmed } => {
+            let timer = Timer::load()?.ok_or(Error::TimerNotRunning)?;
+            if let Some(client) = config.client()? {
+                let crosses_midnight = timer.crosses_midnight()?;
+                let time_entry = timer.into_time_entry(config.timer_rounding)?;
+                if crosses_midnight
+                    && !confirmed
+                    && !confirm(&format!(
+                        "Timer was started before midnight. Log {}h on {}?",
+                        time_entry.hours, time_entry.spent_on
+                    ))?
+                {
+                    println!("Timer is still running.");
+                    return Ok(());
+                }
+                let activities = config
+                    .cache(&client)?
+                    .activities(&[&time_entry.activity_name])
+                    .await?;
+                let time_entry = time_entry.into_request(&activities)?;
+                println!(
+                    "Logging {}h on #{} ({})",
+                    time_entry.hours, time_entry.issue_id, time_entry.spent_on
+                );
+                client.create_time_entry(time_entry).await?;
+                Timer::remove()?;
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            }
+            Ok(())
+        }
+        Command::TimeCancel => {
+            Timer::load()?.ok_or(Error::TimerNotRunning)?;
+            Timer::remove()
+        }
+        Command::Issues {
+            mut filter,
+            offset,
+            limit,
+            columns,
+            format,
+        } => {
+            if let Some(client) = config.client()? {
+                if let Some(project) = &filter.project {
+                    let project = resolve_project(&config.cache(&client)?, project).await?;
+                    filter.project = Some(project);
+                }
+                let issues = client
+                    .issues(&filter, offset, limit, config.page_size())
+                    .await?;
+                match format {
+                    OutputFormat::Table => {
+                        let mut t = term::stdout().ok_or(Error::CannotOpenTerminal)?;
+                        output::print_table(&mut *t, &issues, &columns)?;
+                    }
+                    OutputFormat::Json => output::write_json(&mut io::stdout(), &issues)?,
+                    OutputFormat::Csv | OutputFormat::Tsv => {
+                        output::write_delimited(&mut io::stdout(), &issues, &columns, format)?
+                    }
+                }
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            };
+            Ok(())
+        }
+        Command::IssueShow(id) => {
+            if let Some(client) = config.client()? {
+                let issue = client.issue(id).await?;
+                let mut names = DetailNames::from_issue(&issue);
+                let cache = config.cache(&client)?;
+                if names.is_missing(output::STATUS_ATTRIBUTE) {
+                    names.add(output::STATUS_ATTRIBUTE, &cache.statuses(&[]).await?);
+                }
+                if names.is_missing(output::TRACKER_ATTRIBUTE) {
+                    names.add(output::TRACKER_ATTRIBUTE, &cache.trackers(&[]).await?);
+                }
+                if names.is_missing(output::PRIORITY_ATTRIBUTE) {
+                    names.add(output::PRIORITY_ATTRIBUTE, &cache.priorities(&[]).await?);
+                }
+                let mut t = term::stdout().ok_or(Error::CannotOpenTerminal)?;
+                output::print_issue(&mut *t, &issue, &names)?;
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            };
+            Ok(())
+        }
+        Command::IssueNew(mut new_issue) => {
+            if let Some(client) = config.client()? {
+                let cache = config.cache(&client)?;
+                let trackers = cache.trackers(&[&new_issue.tracker_name]).await?;
+                let statuses = match &new_issue.status_name {
+                    Some(status_name) => cache.statuses(&[status_name]).await?,
+                    None => Vec::new(),
+                };
+                let priorities = match &new_issue.priority_name {
+                    Some(priority_name) => cache.priorities(&[priority_name]).await?,
+                    None => Vec::new(),
+                };
+                new_issue.project = resolve_project(&cache, &new_issue.project).await?;
+                let assigned_to_id = match &new_issue.assignee {
+                    Some(assignee) => Some(resolve_assignee(&client, assignee).await?),
+                    None => None,
+                };
+                let issue =
+                    new_issue.into_request(&trackers, &statuses, &priorities, assigned_to_id)?;
+                let issue = client.create_issue(issue).await?;
+                println!(
+                    "Created issue #{}: {}",
+                    issue.id,
+                    client.issue_url(issue.id)?
+                );
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            };
+            Ok(())
+        }
+        Command::IssueUpdate {
+            id,
+            changes,
+            time_entry,
+        } => {
+            if let Some(client) = config.client()? {
+                // resolve all names before sending anything, so that a typo
+                // does not leave time logged on an issue that was not updated
+                let cache = config.cache(&client)?;
+                let activities = match &time_entry {
+                    Some(time_entry) => cache.activities(&[&time_entry.activity_name]).await?,
+                    None => Vec::new(),
+                };
+                let statuses = match &changes.status_name {
+                    Some(status_name) => cache.statuses(&[status_name]).await?,
+                    None => Vec::new(),
+                };
+                let assigned_to_id = match &changes.assignee {
+                    Some(assignee) => Some(resolve_assignee(&client, assignee).await?),
+                    None => None,
+                };
+                let (time_entry, update) = prepare_issue_update(
+                    changes,
+                    time_entry,
+                    &activities,
+                    &statuses,
+                    assigned_to_id,
+                )?;
+                // log time first, as servers may refuse time logged on closed issues
+                if let Some(time_entry) = time_entry {
+                    client.create_time_entry(time_entry).await?;
+                }
+                if let Some(update) = update {
+                    client.update_issue(id, update).await?;
+                }
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            };
+            Ok(())
+        }
+        Command::CacheRefresh => {
+            if let Some(client) = config.client()? {
+                config.cache(&client)?.refresh().await?;
+            } else {
+                println!("Server details not set. Please use \"login\" command first.");
+            };
+            Ok(())
+        }
+        Command::CacheClear => cache::clear(),
+    }
+}
+
+/// Deletes the stored API key of a removed profile. The profile is already
+/// gone from the config, so a keyring failure is only reported.
+fn forget_api_key(name: &str, profile: &config::Profile) {
+    if let Err(error) = profile.forget_api_key() {
+        eprintln!(
+            "Warning: API key of profile \"{}\" not deleted: {}",
+            name, error
+        );
+    }
+}
+
+fn print_timer(timer: &Timer) -> Result<()> {
+    let elapsed = timer.elapsed()?;
+    println!(
+        "#{}   {}   {}\nstarted at {}, running for {}:{:02}",
+        timer.issue_id,
+        timer.activity_name,
+        timer.comments.as_deref().unwrap_or(""),
+        timer.started_at()?.format("%Y-%m-%d %H:%M"),
+        elapsed.num_hours(),
+        elapsed.num_minutes() % 60
+    );
+    Ok(())
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+fn print_time_entry(label: &str, entry: &response::TimeEntry) {
+    println!(
+        "{:7}{}   {}h   #{}   {}   {}",
+        format!("{}:", label),
+        entry.spent_on,
+        entry.hours,
+        entry.issue.id,
+        entry.activity.name,
+        entry.comments
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(status_name: Option<&str>) -> IssueChanges {
+        IssueChanges {
+            status_name: status_name.map(str::to_string),
+            assignee: None,
+            done_ratio: None,
+            version_id: None,
+            notes: None,
+        }
+    }
+
+    fn time_entry() -> TimeEntry {
+        TimeEntry {
+            issue_id: 12,
+            spent_on: NaiveDate::from_ymd(2019, 8, 19),
+            hours: 1.0,
+            activity_name: "Development".to_string(),
+            comments: None,
+        }
+    }
+
+    fn activities() -> Vec<response::TimeEntryActivity> {
+        vec![response::TimeEntryActivity {
+            id: 9,
+            name: "Development".to_string(),
+        }]
+    }
+
+    fn statuses() -> Vec<response::Reference> {
+        vec![response::Reference {
+            id: 5,
+            name: "Closed".to_string(),
+        }]
+    }
+
+    fn login_api_key_from(args: &[&str]) -> clap::Result<Option<String>> {
+        let matches = cli::build_cli().get_matches_from_safe(args)?;
+        let login_matches = matches.subcommand_matches("login").unwrap();
+        login_api_key(&matches, login_matches)
+    }
+
+    #[test]
+    fn login_password_stdin_ignores_api_key_from_environment() {
+        // the only test using the variable, so that tests running in parallel are not affected
+        std::env::set_var("READMINE_API_KEY", "env-key");
+        let url = "https://redmine.example.com";
+
+        assert_eq!(
+            login_api_key_from(&["readmine", "login", url]).unwrap(),
+            Some("env-key".to_string())
+        );
+        assert_eq!(
+            login_api_key_from(&["readmine", "login", "--password-stdin", url]).unwrap(),
+            None
+        );
+        assert!(login_api_key_from(&[
+            "readmine",
+            "--api-key",
+            "flag-key",
+            "login",
+            "--password-stdin",
+            url
+        ])
+        .is_err());
+        assert!(login_api_key_from(&[
+            "readmine",
+            "login",
+            "--api-key",
+            "flag-key",
+            "--password-stdin",
+            url
+        ])
+        .is_err());
+
+        std::env::remove_var("READMINE_API_KEY");
+    }
+
+    fn time_entry_changes(activity_name: Option<&str>) -> TimeEntryChanges {
+        TimeEntryChanges {
+            issue_id: None,
+            spent_on: None,
+            hours: Some(2.0),
+            activity_name: activity_name.map(str::to_string),
+            comments: None,
+        }
+    }
+
+    #[test]
+    fn time_entry_changes_with_activity() {
+        let update = time_entry_changes(Some("development"))
+            .into_request(&activities())
+            .unwrap();
+        assert_eq!(update.activity_id, Some(9));
+        assert_eq!(update.hours, Some(2.0));
+    }
+
+    #[test]
+    fn time_entry_changes_without_activity() {
+        let update = time_entry_changes(None).into_request(&[]).unwrap();
+        assert_eq!(update.activity_id, None);
+    }
+
+    #[test]
+    fn time_entry_changes_invalid_activity() {
+        match time_entry_changes(Some("Testing")).into_request(&activities()) {
+            Err(Error::InvalidActivityName(name, _)) => assert_eq!(name, "Testing"),
+            _ => panic!("invalid activity accepted"),
+        }
+    }
+
+    #[test]
+    fn time_entry_ids() {
+        assert_eq!(parse_time_entry_id("1234").unwrap(), 1234);
+        assert_eq!(parse_time_entry_id(" 1234 ").unwrap(), 1234);
+        match parse_time_entry_id("#1234") {
+            Err(Error::InvalidTimeEntryId(input)) => assert_eq!(input, "#1234"),
+            _ => panic!("invalid time entry id accepted"),
+        }
+    }
+
+    #[test]
+    fn time_entries_not_deleted_message() {
+        assert_eq!(
+            Error::TimeEntriesNotDeleted(vec![12, 34]).to_string(),
+            "Cannot delete time entries: #12, #34"
+        );
+    }
+
+    #[test]
+    fn issue_new_estimated_more_than_a_day() {
+        let matches = cli::build_cli()
+            .get_matches_from_safe([
+                "readmine",
+                "issue",
+                "new",
+                "--project",
+                "readmine",
+                "--tracker",
+                "Feature",
+                "--subject",
+                "Subject",
+                "--estimated",
+                "40",
+            ])
+            .unwrap();
+        let new_matches = matches
+            .subcommand_matches("issue")
+            .and_then(|matches| matches.subcommand_matches("new"))
+            .unwrap();
+        assert_eq!(estimated_hours(new_matches).unwrap(), Some(40.0));
+    }
+
+    #[test]
+    fn issue_update_with_time_entry() {
+        let (time_entry, update) = prepare_issue_update(
+            changes(Some("closed")),
+            Some(time_entry()),
+            &activities(),
+            &statuses(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(time_entry.unwrap().activity_id, 9);
+        assert_eq!(update.unwrap().status_id, Some(5));
+    }
+
+    #[test]
+    fn issue_update_with_time_entry_only() {
+        let (time_entry, update) =
+            prepare_issue_update(changes(None), Some(time_entry()), &activities(), &[], None)
+                .unwrap();
+        assert!(time_entry.is_some());
+        assert!(update.is_none());
+    }
+
+    #[test]
+    fn names_found_ignoring_case() {
+        assert_eq!(find_activity("development", &activities()).unwrap().id, 9);
+        assert_eq!(find_by_name("status", "CLOSED", &statuses()).unwrap().id, 5);
+        assert!(find_activity("Design", &activities()).is_err());
+    }
+
+    #[test]
+    fn issue_update_invalid_status_sends_no_request() {
+        match prepare_issue_update(
+            changes(Some("Nonexistent")),
+            Some(time_entry()),
+            &activities(),
+            &statuses(),
+            None,
+        ) {
+            Err(Error::InvalidName("status", name, _)) => assert_eq!(name, "Nonexistent"),
+            _ => panic!("issue update with an invalid status prepared"),
+        }
     }
 }
@@ -0,0 +1,887 @@
+use crate::app_error::Error;
+use crate::app_error::Result;
+use crate::grouping::{Group, GroupKey, GroupedTimeEntries};
+use chrono::NaiveDate;
+use readmine::constants::{DATE_FORMAT, DATE_TIME_FORMAT};
+use readmine::response::{Issue, JournalDetail, Reference, TimeEntry};
+use serde::Serialize;
+use serde_json;
+use std::collections::HashMap;
+use std::io::Write;
+use term::{self, StdoutTerminal};
+
+const COLUMN_SEPARATOR: &str = "   ";
+const GROUP_INDENT: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    pub fn parse(input: &str) -> Result<OutputFormat> {
+        match input.trim().to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(Error::InvalidOutputFormat(input.to_owned())),
+        }
+    }
+}
+
+/// A column of a table printed in the terminal or written as CSV/TSV.
+pub trait Column: Copy + Sized + 'static {
+    type Item;
+
+    const ALL: &'static [Self];
+
+    fn name(self) -> &'static str;
+
+    /// Plain value, as written to CSV/TSV.
+    fn value(self, item: &Self::Item) -> String;
+
+    /// Value as shown in the terminal.
+    fn formatted_value(self, item: &Self::Item) -> String {
+        self.value(item)
+    }
+
+    fn set_style(self, _t: &mut StdoutTerminal) -> Result<()> {
+        Ok(())
+    }
+
+    fn parse_list(input: &str) -> Result<Vec<Self>> {
+        input.split(',').map(str::trim).map(Self::parse).collect()
+    }
+
+    fn parse(input: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .find(|column| column.name().eq_ignore_ascii_case(input))
+            .cloned()
+            .ok_or_else(|| {
+                let names = Self::ALL
+                    .iter()
+                    .map(|column| column.name().to_string())
+                    .collect();
+                Error::InvalidColumnName(input.to_owned(), names)
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeEntryColumn {
+    Id,
+    Date,
+    Hours,
+    Issue,
+    Project,
+    Activity,
+    User,
+    Comment,
+    Created,
+    Updated,
+}
+
+const ALL_TIME_ENTRY_COLUMNS: &[TimeEntryColumn] = &[
+    TimeEntryColumn::Id,
+    TimeEntryColumn::Date,
+    TimeEntryColumn::Hours,
+    TimeEntryColumn::Issue,
+    TimeEntryColumn::Project,
+    TimeEntryColumn::Activity,
+    TimeEntryColumn::User,
+    TimeEntryColumn::Comment,
+    TimeEntryColumn::Created,
+    TimeEntryColumn::Updated,
+];
+
+pub const DEFAULT_TIME_ENTRY_COLUMNS: &[TimeEntryColumn] = &[
+    TimeEntryColumn::Date,
+    TimeEntryColumn::Hours,
+    TimeEntryColumn::Issue,
+    TimeEntryColumn::Project,
+    TimeEntryColumn::Activity,
+    TimeEntryColumn::Comment,
+];
+
+impl Column for TimeEntryColumn {
+    type Item = TimeEntry;
+
+    const ALL: &'static [TimeEntryColumn] = ALL_TIME_ENTRY_COLUMNS;
+
+    fn name(self) -> &'static str {
+        match self {
+            TimeEntryColumn::Id => "id",
+            TimeEntryColumn::Date => "date",
+            TimeEntryColumn::Hours => "hours",
+            TimeEntryColumn::Issue => "issue",
+            TimeEntryColumn::Project => "project",
+            TimeEntryColumn::Activity => "activity",
+            TimeEntryColumn::User => "user",
+            TimeEntryColumn::Comment => "comment",
+            TimeEntryColumn::Created => "created",
+            TimeEntryColumn::Updated => "updated",
+        }
+    }
+
+    fn value(self, entry: &TimeEntry) -> String {
+        match self {
+            TimeEntryColumn::Id => entry.id.to_string(),
+            TimeEntryColumn::Date => entry.spent_on.format(DATE_FORMAT).to_string(),
+            TimeEntryColumn::Hours => entry.hours.to_string(),
+            TimeEntryColumn::Issue => entry.issue.id.to_string(),
+            TimeEntryColumn::Project => entry.project.name.clone(),
+            TimeEntryColumn::Activity => entry.activity.name.clone(),
+            TimeEntryColumn::User => entry.user.name.clone(),
+            TimeEntryColumn::Comment => entry.comments.clone(),
+            TimeEntryColumn::Created => entry.created_on.format(DATE_TIME_FORMAT).to_string(),
+            TimeEntryColumn::Updated => entry.updated_on.format(DATE_TIME_FORMAT).to_string(),
+        }
+    }
+
+    fn formatted_value(self, entry: &TimeEntry) -> String {
+        match self {
+            TimeEntryColumn::Hours => format!("{}h", entry.hours),
+            TimeEntryColumn::Issue => format!("#{}", entry.issue.id),
+            _ => self.value(entry),
+        }
+    }
+
+    fn set_style(self, t: &mut StdoutTerminal) -> Result<()> {
+        match self {
+            TimeEntryColumn::Date => t.fg(term::color::WHITE)?,
+            TimeEntryColumn::Hours => {
+                t.attr(term::Attr::Bold)?;
+                t.fg(term::color::WHITE)?;
+            }
+            TimeEntryColumn::Comment => t.fg(term::color::YELLOW)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IssueColumn {
+    Id,
+    Project,
+    Tracker,
+    Status,
+    Priority,
+    Assignee,
+    Author,
+    Version,
+    Subject,
+    Done,
+    Start,
+    Due,
+    Estimated,
+    Created,
+    Updated,
+}
+
+const ALL_ISSUE_COLUMNS: &[IssueColumn] = &[
+    IssueColumn::Id,
+    IssueColumn::Project,
+    IssueColumn::Tracker,
+    IssueColumn::Status,
+    IssueColumn::Priority,
+    IssueColumn::Assignee,
+    IssueColumn::Author,
+    IssueColumn::Version,
+    IssueColumn::Subject,
+    IssueColumn::Done,
+    IssueColumn::Start,
+    IssueColumn::Due,
+    IssueColumn::Estimated,
+    IssueColumn::Created,
+    IssueColumn::Updated,
+];
+
+pub const DEFAULT_ISSUE_COLUMNS: &[IssueColumn] = &[
+    IssueColumn::Id,
+    IssueColumn::Project,
+    IssueColumn::Tracker,
+    IssueColumn::Status,
+    IssueColumn::Priority,
+    IssueColumn::Assignee,
+    IssueColumn::Subject,
+];
+
+impl Column for IssueColumn {
+    type Item = Issue;
+
+    const ALL: &'static [IssueColumn] = ALL_ISSUE_COLUMNS;
+
+    fn name(self) -> &'static str {
+        match self {
+            IssueColumn::Id => "id",
+            IssueColumn::Project => "project",
+            IssueColumn::Tracker => "tracker",
+            IssueColumn::Status => "status",
+            IssueColumn::Priority => "priority",
+            IssueColumn::Assignee => "assignee",
+            IssueColumn::Author => "author",
+            IssueColumn::Version => "version",
+            IssueColumn::Subject => "subject",
+            IssueColumn::Done => "done",
+            IssueColumn::Start => "start",
+            IssueColumn::Due => "due",
+            IssueColumn::Estimated => "estimated",
+            IssueColumn::Created => "created",
+            IssueColumn::Updated => "updated",
+        }
+    }
+
+    fn value(self, issue: &Issue) -> String {
+        let date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format(DATE_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+        match self {
+            IssueColumn::Id => issue.id.to_string(),
+            IssueColumn::Project => issue.project.name.clone(),
+            IssueColumn::Tracker => issue.tracker.name.clone(),
+            IssueColumn::Status => issue.status.name.clone(),
+            IssueColumn::Priority => issue.priority.name.clone(),
+            IssueColumn::Assignee => issue
+                .assigned_to
+                .as_ref()
+                .map(|user| user.name.clone())
+                .unwrap_or_default(),
+            IssueColumn::Author => issue.author.name.clone(),
+            IssueColumn::Version => issue
+                .fixed_version
+                .as_ref()
+                .map(|version| version.name.clone())
+                .unwrap_or_default(),
+            IssueColumn::Subject => issue.subject.clone(),
+            IssueColumn::Done => issue.done_ratio.to_string(),
+            IssueColumn::Start => date(issue.start_date),
+            IssueColumn::Due => date(issue.due_date),
+            IssueColumn::Estimated => issue
+                .estimated_hours
+                .map(|hours| hours.to_string())
+                .unwrap_or_default(),
+            IssueColumn::Created => issue.created_on.format(DATE_TIME_FORMAT).to_string(),
+            IssueColumn::Updated => issue.updated_on.format(DATE_TIME_FORMAT).to_string(),
+        }
+    }
+
+    fn formatted_value(self, issue: &Issue) -> String {
+        match self {
+            IssueColumn::Id => format!("#{}", issue.id),
+            IssueColumn::Done => format!("{}%", issue.done_ratio),
+            IssueColumn::Estimated => issue
+                .estimated_hours
+                .map(|hours| format!("{}h", hours))
+                .unwrap_or_default(),
+            _ => self.value(issue),
+        }
+    }
+
+    fn set_style(self, t: &mut StdoutTerminal) -> Result<()> {
+        match self {
+            IssueColumn::Id => {
+                t.attr(term::Attr::Bold)?;
+                t.fg(term::color::WHITE)?;
+            }
+            IssueColumn::Status => t.fg(term::color::WHITE)?,
+            IssueColumn::Subject => t.fg(term::color::YELLOW)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub fn print_time_entries(
+    t: &mut StdoutTerminal,
+    time_entries: &[TimeEntry],
+    columns: &[TimeEntryColumn],
+) -> Result<()> {
+    print_table(t, time_entries, columns)?;
+    let total = time_entries
+        .iter()
+        .fold(0.0, |sum, entry| sum + entry.hours);
+    print_total(t, total)
+}
+
+pub fn print_table<C: Column>(
+    t: &mut StdoutTerminal,
+    items: &[C::Item],
+    columns: &[C],
+) -> Result<()> {
+    let widths: Vec<_> = columns
+        .iter()
+        .map(|column| {
+            items
+                .iter()
+                .map(|item| column.formatted_value(item).chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for item in items {
+        for (index, (column, width)) in columns.iter().zip(&widths).enumerate() {
+            if index > 0 {
+                write!(t, "{}", COLUMN_SEPARATOR)?;
+            }
+            column.set_style(t)?;
+            if index + 1 < columns.len() {
+                write!(t, "{:width$}", column.formatted_value(item), width = width)?;
+            } else {
+                write!(t, "{}", column.formatted_value(item))?;
+            }
+            t.reset()?;
+        }
+        writeln!(t)?;
+    }
+
+    Ok(())
+}
+
+pub fn print_groups(t: &mut StdoutTerminal, grouped: &GroupedTimeEntries) -> Result<()> {
+    let name_width = max_group_name_width(&grouped.groups, 0);
+    let hours_width = max_group_hours_width(&grouped.groups);
+    print_group_level(t, &grouped.groups, 0, name_width, hours_width)?;
+    print_total(t, grouped.hours)
+}
+
+fn print_group_level(
+    t: &mut StdoutTerminal,
+    groups: &[Group],
+    depth: usize,
+    name_width: usize,
+    hours_width: usize,
+) -> Result<()> {
+    for group in groups {
+        let indent = depth * GROUP_INDENT;
+        t.fg(term::color::WHITE)?;
+        write!(
+            t,
+            "{:indent$}{:width$}{}",
+            "",
+            group.name,
+            COLUMN_SEPARATOR,
+            indent = indent,
+            width = name_width - indent
+        )?;
+        t.attr(term::Attr::Bold)?;
+        t.fg(term::color::WHITE)?;
+        write!(
+            t,
+            "{:>width$}",
+            format!("{}h", group.hours),
+            width = hours_width
+        )?;
+        t.reset()?;
+        writeln!(t, "{}{:5.1}%", COLUMN_SEPARATOR, group.percentage)?;
+        print_group_level(t, &group.groups, depth + 1, name_width, hours_width)?;
+    }
+    Ok(())
+}
+
+fn max_group_name_width(groups: &[Group], depth: usize) -> usize {
+    groups
+        .iter()
+        .map(|group| {
+            let width = depth * GROUP_INDENT + group.name.chars().count();
+            width.max(max_group_name_width(&group.groups, depth + 1))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn max_group_hours_width(groups: &[Group]) -> usize {
+    groups
+        .iter()
+        .map(|group| {
+            let width = format!("{}h", group.hours).len();
+            width.max(max_group_hours_width(&group.groups))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+pub const STATUS_ATTRIBUTE: &str = "status_id";
+pub const TRACKER_ATTRIBUTE: &str = "tracker_id";
+pub const PRIORITY_ATTRIBUTE: &str = "priority_id";
+const ASSIGNEE_ATTRIBUTE: &str = "assigned_to_id";
+const VERSION_ATTRIBUTE: &str = "fixed_version_id";
+const PROJECT_ATTRIBUTE: &str = "project_id";
+
+/// Names of the ids changed in issue journals, e.g. of statuses for `status_id`.
+#[derive(Default)]
+pub struct DetailNames {
+    names: HashMap<&'static str, HashMap<String, String>>,
+    used: Vec<(String, String)>,
+}
+
+impl DetailNames {
+    /// Names known from the issue itself: its current status, assignee, watchers etc.
+    pub fn from_issue(issue: &Issue) -> Self {
+        let mut names = DetailNames::default();
+        names.add(STATUS_ATTRIBUTE, Some(&issue.status));
+        names.add(TRACKER_ATTRIBUTE, Some(&issue.tracker));
+        names.add(PRIORITY_ATTRIBUTE, Some(&issue.priority));
+        names.add(PROJECT_ATTRIBUTE, Some(&issue.project));
+        names.add(VERSION_ATTRIBUTE, &issue.fixed_version);
+        let users = issue
+            .assigned_to
+            .iter()
+            .chain(Some(&issue.author))
+            .chain(&issue.watchers)
+            .chain(issue.journals.iter().map(|journal| &journal.user));
+        names.add(ASSIGNEE_ATTRIBUTE, users);
+        for detail in issue.journals.iter().flat_map(|journal| &journal.details) {
+            if detail.property == "attr" {
+                for value in detail.old_value.iter().chain(&detail.new_value) {
+                    names.used.push((detail.name.clone(), value.clone()));
+                }
+            }
+        }
+        names
+    }
+
+    pub fn add<'r, I>(&mut self, attribute: &'static str, references: I)
+    where
+        I: IntoIterator<Item = &'r Reference>,
+    {
+        let names = self.names.entry(attribute).or_default();
+        for reference in references {
+            names.insert(reference.id.to_string(), reference.name.clone());
+        }
+    }
+
+    /// Checks whether the journals change the attribute to or from an id of unknown name.
+    pub fn is_missing(&self, attribute: &str) -> bool {
+        self.used
+            .iter()
+            .any(|(name, value)| name == attribute && self.name(name, value).is_none())
+    }
+
+    fn name(&self, attribute: &str, value: &str) -> Option<&str> {
+        self.names.get(attribute)?.get(value).map(String::as_str)
+    }
+
+    /// The name of the value if known, the value itself otherwise.
+    fn value_text<'v>(&'v self, attribute: &str, value: &'v str) -> &'v str {
+        self.name(attribute, value).unwrap_or(value)
+    }
+}
+
+pub fn print_issue(t: &mut StdoutTerminal, issue: &Issue, names: &DetailNames) -> Result<()> {
+    t.attr(term::Attr::Bold)?;
+    t.fg(term::color::WHITE)?;
+    write!(t, "#{}", issue.id)?;
+    t.reset()?;
+    write!(t, " {}: ", issue.tracker.name)?;
+    t.fg(term::color::YELLOW)?;
+    write!(t, "{}", issue.subject)?;
+    t.reset()?;
+    writeln!(t)?;
+
+    print_issue_field(t, "Project", &issue.project.name)?;
+    print_issue_field(t, "Status", &issue.status.name)?;
+    print_issue_field(t, "Priority", &issue.priority.name)?;
+    print_issue_field(t, "Author", &issue.author.name)?;
+    if let Some(assignee) = &issue.assigned_to {
+        print_issue_field(t, "Assignee", &assignee.name)?;
+    }
+    if let Some(version) = &issue.fixed_version {
+        print_issue_field(t, "Version", &version.name)?;
+    }
+    if let Some(parent) = &issue.parent {
+        print_issue_field(t, "Parent", &format!("#{}", parent.id))?;
+    }
+    if let Some(start_date) = issue.start_date {
+        print_issue_field(t, "Start", &start_date.format(DATE_FORMAT).to_string())?;
+    }
+    if let Some(due_date) = issue.due_date {
+        print_issue_field(t, "Due", &due_date.format(DATE_FORMAT).to_string())?;
+    }
+    print_issue_field(t, "Done", &format!("{}%", issue.done_ratio))?;
+    print_issue_field(t, "Time", &spent_hours_text(issue))?;
+    if !issue.watchers.is_empty() {
+        let names: Vec<_> = issue
+            .watchers
+            .iter()
+            .map(|user| user.name.as_str())
+            .collect();
+        print_issue_field(t, "Watchers", &names.join(", "))?;
+    }
+
+    if let Some(description) = issue.description.as_ref().filter(|d| !d.trim().is_empty()) {
+        writeln!(t)?;
+        print_indented(t, description)?;
+    }
+
+    if !issue.children.is_empty() {
+        print_issue_section(t, "Subtasks")?;
+        for child in &issue.children {
+            writeln!(
+                t,
+                "  #{} {}: {}",
+                child.id, child.tracker.name, child.subject
+            )?;
+        }
+    }
+
+    if !issue.relations.is_empty() {
+        print_issue_section(t, "Related issues")?;
+        for relation in &issue.relations {
+            let other_id = if relation.issue_id == issue.id {
+                relation.issue_to_id
+            } else {
+                relation.issue_id
+            };
+            writeln!(t, "  {} #{}", relation.relation_type, other_id)?;
+        }
+    }
+
+    if !issue.attachments.is_empty() {
+        print_issue_section(t, "Files")?;
+        for attachment in &issue.attachments {
+            writeln!(
+                t,
+                "  {} ({} bytes, {})",
+                attachment.filename, attachment.filesize, attachment.author.name
+            )?;
+        }
+    }
+
+    if !issue.journals.is_empty() {
+        print_issue_section(t, "History")?;
+        let mut journals: Vec<_> = issue.journals.iter().collect();
+        journals.sort_by_key(|journal| journal.created_on);
+        for journal in journals {
+            t.fg(term::color::WHITE)?;
+            write!(t, "{}", journal.created_on.format("%Y-%m-%d %H:%M"))?;
+            t.reset()?;
+            t.attr(term::Attr::Bold)?;
+            write!(t, "{}{}", COLUMN_SEPARATOR, journal.user.name)?;
+            t.reset()?;
+            writeln!(t)?;
+            for detail in &journal.details {
+                writeln!(t, "  {}", journal_detail_text(detail, names))?;
+            }
+            if let Some(notes) = journal.notes.as_ref().filter(|n| !n.trim().is_empty()) {
+                t.fg(term::color::YELLOW)?;
+                print_indented(t, notes)?;
+                t.reset()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_issue_field(t: &mut StdoutTerminal, name: &str, value: &str) -> Result<()> {
+    t.fg(term::color::WHITE)?;
+    write!(t, "{:10}", format!("{}:", name))?;
+    t.reset()?;
+    writeln!(t, "{}", value)?;
+    Ok(())
+}
+
+fn print_issue_section(t: &mut StdoutTerminal, name: &str) -> Result<()> {
+    writeln!(t)?;
+    t.attr(term::Attr::Bold)?;
+    t.fg(term::color::WHITE)?;
+    write!(t, "{}", name)?;
+    t.reset()?;
+    writeln!(t)?;
+    Ok(())
+}
+
+fn print_indented(t: &mut StdoutTerminal, text: &str) -> Result<()> {
+    for line in text.lines() {
+        writeln!(t, "  {}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+fn spent_hours_text(issue: &Issue) -> String {
+    let spent = format!("{}h spent", issue.spent_hours.unwrap_or(0.0));
+    match issue.estimated_hours {
+        Some(estimated) => format!("{} of {}h estimated", spent, estimated),
+        None => spent,
+    }
+}
+
+fn journal_detail_text(detail: &JournalDetail, names: &DetailNames) -> String {
+    let (name, old_value, new_value) = if detail.property == "attr" {
+        let value = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| names.value_text(&detail.name, value).to_string())
+        };
+        (
+            detail.name.trim_end_matches("_id").replace('_', " "),
+            value(&detail.old_value),
+            value(&detail.new_value),
+        )
+    } else {
+        (
+            detail.name.clone(),
+            detail.old_value.clone(),
+            detail.new_value.clone(),
+        )
+    };
+    match (old_value, new_value) {
+        (Some(old_value), Some(new_value)) => {
+            format!("{} changed from {} to {}", name, old_value, new_value)
+        }
+        (None, Some(new_value)) => format!("{} set to {}", name, new_value),
+        (Some(old_value), None) => format!("{} deleted ({})", name, old_value),
+        (None, None) => format!("{} changed", name),
+    }
+}
+
+fn print_total(t: &mut StdoutTerminal, total: f32) -> Result<()> {
+    t.fg(term::color::WHITE)?;
+    write!(t, "Total time: ")?;
+    t.attr(term::Attr::Bold)?;
+    t.fg(term::color::WHITE)?;
+    write!(t, "{}", total)?;
+    t.reset()?;
+    writeln!(t, "h")?;
+    Ok(())
+}
+
+pub fn write_json<W: Write, T: Serialize + ?Sized>(out: &mut W, value: &T) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn write_delimited<W: Write, C: Column>(
+    out: &mut W,
+    items: &[C::Item],
+    columns: &[C],
+    format: OutputFormat,
+) -> Result<()> {
+    let header: Vec<_> = columns
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    write_record(out, &header, format)?;
+    for item in items {
+        let record: Vec<_> = columns.iter().map(|column| column.value(item)).collect();
+        write_record(out, &record, format)?;
+    }
+    Ok(())
+}
+
+pub fn write_groups_delimited<W: Write>(
+    out: &mut W,
+    grouped: &GroupedTimeEntries,
+    keys: &[GroupKey],
+    format: OutputFormat,
+) -> Result<()> {
+    let mut header: Vec<_> = keys.iter().map(|key| key.name().to_string()).collect();
+    header.push("hours".to_string());
+    header.push("percentage".to_string());
+    write_record(out, &header, format)?;
+    write_group_records(out, &grouped.groups, &mut Vec::new(), keys.len(), format)
+}
+
+fn write_group_records<W: Write>(
+    out: &mut W,
+    groups: &[Group],
+    names: &mut Vec<String>,
+    depth: usize,
+    format: OutputFormat,
+) -> Result<()> {
+    for group in groups {
+        names.push(group.name.clone());
+        let mut record = names.clone();
+        record.resize(depth, String::new());
+        record.push(group.hours.to_string());
+        record.push(format!("{:.1}", group.percentage));
+        write_record(out, &record, format)?;
+        write_group_records(out, &group.groups, names, depth, format)?;
+        names.pop();
+    }
+    Ok(())
+}
+
+fn write_record<W: Write>(out: &mut W, fields: &[String], format: OutputFormat) -> Result<()> {
+    let (separator, line_end) = match format {
+        OutputFormat::Csv => (",", "\r\n"),
+        _ => ("\t", "\n"),
+    };
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|field| match format {
+            OutputFormat::Csv => csv_field(field),
+            _ => tsv_field(field),
+        })
+        .collect();
+    write!(out, "{}{}", fields.join(separator), line_end)?;
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn tsv_field(field: &str) -> String {
+    field.replace(&['\t', '\r', '\n'][..], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_column() {
+        assert_eq!(
+            TimeEntryColumn::parse_list("id").unwrap(),
+            vec![TimeEntryColumn::Id]
+        );
+    }
+
+    #[test]
+    fn parse_column_list() {
+        assert_eq!(
+            TimeEntryColumn::parse_list("id,date,hours,user").unwrap(),
+            vec![
+                TimeEntryColumn::Id,
+                TimeEntryColumn::Date,
+                TimeEntryColumn::Hours,
+                TimeEntryColumn::User
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_column_list_with_spaces_and_capitals() {
+        assert_eq!(
+            TimeEntryColumn::parse_list(" Created , UPDATED ").unwrap(),
+            vec![TimeEntryColumn::Created, TimeEntryColumn::Updated]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_column() {
+        assert!(TimeEntryColumn::parse_list("id,foo").is_err());
+    }
+
+    #[test]
+    fn parse_issue_columns() {
+        assert_eq!(
+            IssueColumn::parse_list("id,Assignee,due").unwrap(),
+            vec![IssueColumn::Id, IssueColumn::Assignee, IssueColumn::Due]
+        );
+        assert!(IssueColumn::parse_list("hours").is_err());
+    }
+
+    fn journal_detail(old_value: Option<&str>, new_value: Option<&str>) -> JournalDetail {
+        JournalDetail {
+            property: "attr".to_string(),
+            name: "assigned_to_id".to_string(),
+            old_value: old_value.map(str::to_string),
+            new_value: new_value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn journal_detail_changed() {
+        assert_eq!(
+            journal_detail_text(
+                &journal_detail(Some("3"), Some("5")),
+                &DetailNames::default()
+            ),
+            "assigned to changed from 3 to 5"
+        );
+    }
+
+    #[test]
+    fn journal_detail_with_names() {
+        let mut names = DetailNames::default();
+        names.add(
+            ASSIGNEE_ATTRIBUTE,
+            &[Reference {
+                id: 3,
+                name: "John Doe".to_string(),
+            }],
+        );
+        let detail = journal_detail(Some("3"), Some("5"));
+        assert_eq!(
+            journal_detail_text(&detail, &names),
+            "assigned to changed from John Doe to 5"
+        );
+
+        let mut detail = journal_detail(Some("3"), Some("4"));
+        detail.name = "subject".to_string();
+        assert_eq!(
+            journal_detail_text(&detail, &names),
+            "subject changed from 3 to 4"
+        );
+    }
+
+    #[test]
+    fn journal_detail_set_and_deleted() {
+        assert_eq!(
+            journal_detail_text(&journal_detail(None, Some("5")), &DetailNames::default()),
+            "assigned to set to 5"
+        );
+        assert_eq!(
+            journal_detail_text(&journal_detail(Some("3"), None), &DetailNames::default()),
+            "assigned to deleted (3)"
+        );
+    }
+
+    #[test]
+    fn parse_output_format() {
+        assert_eq!(OutputFormat::parse("CSV").unwrap(), OutputFormat::Csv);
+    }
+
+    #[test]
+    fn parse_invalid_output_format() {
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn csv_plain_field() {
+        assert_eq!(csv_field("Development"), "Development");
+    }
+
+    #[test]
+    fn csv_field_with_comma() {
+        assert_eq!(csv_field("review, fixes"), "\"review, fixes\"");
+    }
+
+    #[test]
+    fn csv_field_with_quotes() {
+        assert_eq!(csv_field("the \"big\" one"), "\"the \"\"big\"\" one\"");
+    }
+
+    #[test]
+    fn csv_field_with_line_break() {
+        assert_eq!(csv_field("first\nsecond"), "\"first\nsecond\"");
+    }
+
+    #[test]
+    fn tsv_field_with_tab_and_line_break() {
+        assert_eq!(tsv_field("a\tb\nc"), "a b c");
+    }
+
+    #[test]
+    fn csv_record() {
+        let mut out = Vec::new();
+        let fields = vec!["1".to_string(), "a,b".to_string()];
+        write_record(&mut out, &fields, OutputFormat::Csv).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1,\"a,b\"\r\n");
+    }
+}
@@ -1,111 +1,487 @@
 use crate::constants::DATE_FORMAT;
 use crate::error::Error;
-use crate::response;
+use crate::response::{self, Page};
 use crate::result::Result;
 use crate::serialization_formats::*;
 use crate::time_range::TimeRange;
 use chrono::NaiveDate;
-use reqwest::Client;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use rpassword::read_password_from_tty;
 use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
+use url::Url;
 
-pub async fn login(url: &str, login_name: Option<String>) -> Result<response::User> {
-    let login_name = if let Some(name) = login_name {
-        name
-    } else {
-        print!("Login: ");
-        io::stdout().flush()?;
-        let mut login_name = String::new();
-        io::stdin().read_line(&mut login_name)?;
-        login_name.retain(|c| c != '\n' && c != '\r');
-        login_name
-    };
+const API_KEY_HEADER: &str = "X-Redmine-API-Key";
 
-    let password = read_password_from_tty(Some("Password: "))?;
-    println!();
+/// Authentication used for requests to the Redmine server.
+pub enum Credentials {
+    None,
+    ApiKey(String),
+    Basic { login: String, password: String },
+}
 
-    let url = format!("{}/users/current.json", url);
-    let client = Client::new();
-    let res = client
-        .get(&url)
-        .basic_auth(&login_name, Some(&password))
-        .send()
-        .await?;
-    let status = res.status();
-    if status == reqwest::StatusCode::OK {
-        response::parse_user(&res.text().await?)
-    } else {
-        Err(Error::RequestFailed(status))
+impl From<Option<String>> for Credentials {
+    fn from(api_key: Option<String>) -> Self {
+        api_key.map_or(Credentials::None, Credentials::ApiKey)
     }
 }
 
-pub async fn user(url: &str, api_key: &Option<String>) -> Result<response::User> {
-    let url = format!("{}/users/current.json", url);
-    let client = Client::new();
-    let mut request_builder = client.get(&url);
-    if let Some(api_key) = api_key {
-        request_builder = request_builder.header("X-Redmine-API-Key", api_key.clone());
+/// Client for the Redmine REST API.
+///
+/// The underlying HTTP connection pool is shared by all requests made through
+/// one client, so it should be reused rather than created per request.
+pub struct RedmineClient {
+    url: Url,
+    credentials: Credentials,
+    client: Client,
+}
+
+impl RedmineClient {
+    /// Creates a client for the Redmine instance at `url`.
+    pub fn new(url: &str, credentials: Credentials) -> Result<Self> {
+        let mut url = Url::parse(url)?;
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(RedmineClient {
+            url,
+            credentials,
+            client: Client::new(),
+        })
     }
-    let res = request_builder.send().await?;
-    let status = res.status();
-    if status == reqwest::StatusCode::OK {
-        response::parse_user(&res.text().await?)
-    } else {
-        Err(Error::RequestFailed(status))
+
+    /// Address of the Redmine instance, always ending with a slash.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// Fetches the user the credentials belong to.
+    pub async fn current_user(&self) -> Result<response::User> {
+        let text = self.get("users/current.json", &[]).await?;
+        response::parse_user(&text)
+    }
+
+    /// Fetches all of the current user's time entries within `range`,
+    /// requesting `page_size` entries at a time.
+    pub async fn time_entries(
+        &self,
+        range: &TimeRange,
+        page_size: u32,
+    ) -> Result<Vec<response::TimeEntry>> {
+        let query = [
+            ("user_id", "me".to_string()),
+            ("from", range.from.format(DATE_FORMAT).to_string()),
+            ("to", range.to.format(DATE_FORMAT).to_string()),
+        ];
+        self.get_pages(
+            "time_entries.json",
+            &query,
+            response::parse_time_entries,
+            0,
+            u32::MAX,
+            page_size,
+        )
+        .await
+    }
+
+    /// Fetches up to `limit` issues matching `filter`, skipping the first
+    /// `offset` ones and requesting `page_size` issues at a time.
+    pub async fn issues(
+        &self,
+        filter: &IssueFilter,
+        offset: u32,
+        limit: u32,
+        page_size: u32,
+    ) -> Result<Vec<response::Issue>> {
+        self.get_pages(
+            "issues.json",
+            &filter.query(),
+            response::parse_issues,
+            offset,
+            limit,
+            page_size,
+        )
+        .await
+    }
+
+    /// Fetches a single issue with its journals, subtasks, relations,
+    /// watchers and attachments.
+    pub async fn issue(&self, id: i32) -> Result<response::Issue> {
+        let query = [(
+            "include",
+            "journals,children,relations,watchers,attachments".to_string(),
+        )];
+        let text = self.get(&format!("issues/{}.json", id), &query).await?;
+        response::parse_issue(&text)
+    }
+
+    /// Creates a new issue and returns it as stored by the server.
+    pub async fn create_issue(&self, issue: Issue) -> Result<response::Issue> {
+        let issue_request = IssueRequest { issue };
+        let request_builder = self
+            .request(Method::POST, "issues.json")?
+            .json(&issue_request);
+        let res = self.send(request_builder, StatusCode::CREATED).await?;
+        response::parse_issue(&res.text().await?)
+    }
+
+    /// Changes the given fields of an existing issue.
+    pub async fn update_issue(&self, id: i32, issue: IssueUpdate) -> Result<()> {
+        let issue_request = IssueUpdateRequest { issue };
+        let request_builder = self
+            .request(Method::PUT, &format!("issues/{}.json", id))?
+            .json(&issue_request);
+        self.send_update(request_builder).await
+    }
+
+    /// Address of the issue page in the web interface.
+    pub fn issue_url(&self, id: i32) -> Result<Url> {
+        Ok(self.url.join(&format!("issues/{}", id))?)
+    }
+
+    /// Fetches all projects visible to the user, requesting `page_size`
+    /// projects at a time.
+    pub async fn projects(&self, page_size: u32) -> Result<Vec<response::Project>> {
+        self.get_pages(
+            "projects.json",
+            &[],
+            response::parse_projects,
+            0,
+            u32::MAX,
+            page_size,
+        )
+        .await
+    }
+
+    /// Fetches the list of trackers.
+    pub async fn trackers(&self) -> Result<Vec<response::Reference>> {
+        let text = self.get("trackers.json", &[]).await?;
+        response::parse_trackers(&text)
+    }
+
+    /// Fetches the list of issue statuses.
+    pub async fn issue_statuses(&self) -> Result<Vec<response::Reference>> {
+        let text = self.get("issue_statuses.json", &[]).await?;
+        response::parse_issue_statuses(&text)
+    }
+
+    /// Fetches the list of issue priorities.
+    pub async fn issue_priorities(&self) -> Result<Vec<response::Reference>> {
+        let text = self.get("enumerations/issue_priorities.json", &[]).await?;
+        response::parse_issue_priorities(&text)
+    }
+
+    /// Fetches a single time entry.
+    pub async fn time_entry(&self, id: i32) -> Result<response::TimeEntry> {
+        let text = self.get(&format!("time_entries/{}.json", id), &[]).await?;
+        response::parse_time_entry(&text)
+    }
+
+    /// Fetches the list of time entry activities.
+    pub async fn time_entry_activities(&self) -> Result<Vec<response::TimeEntryActivity>> {
+        let text = self
+            .get("enumerations/time_entry_activities.json", &[])
+            .await?;
+        response::parse_time_entry_activities(&text)
+    }
+
+    /// Logs a new time entry.
+    pub async fn create_time_entry(&self, time_entry: TimeEntry) -> Result<()> {
+        let time_entry_request = TimeEntryRequest { time_entry };
+        let request_builder = self
+            .request(Method::POST, "time_entries.json")?
+            .json(&time_entry_request);
+        self.send(request_builder, StatusCode::CREATED).await?;
+        Ok(())
+    }
+
+    /// Changes the given fields of an existing time entry.
+    pub async fn update_time_entry(&self, id: i32, time_entry: TimeEntryUpdate) -> Result<()> {
+        let time_entry_request = TimeEntryUpdateRequest { time_entry };
+        let request_builder = self
+            .request(Method::PUT, &format!("time_entries/{}.json", id))?
+            .json(&time_entry_request);
+        self.send_update(request_builder).await
+    }
+
+    /// Deletes a time entry.
+    pub async fn delete_time_entry(&self, id: i32) -> Result<()> {
+        let request_builder = self.request(Method::DELETE, &format!("time_entries/{}.json", id))?;
+        self.send_update(request_builder).await
+    }
+
+    /// Fetches up to `limit` items of the paged list at `path`, skipping the first
+    /// `offset` ones and requesting `page_size` items at a time.
+    async fn get_pages<P: Page>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        parse: fn(&str) -> Result<P>,
+        offset: u32,
+        limit: u32,
+        page_size: u32,
+    ) -> Result<Vec<P::Item>> {
+        let mut items = Vec::new();
+        let mut offset = offset;
+
+        while (items.len() as u32) < limit {
+            let mut page_query = query.to_vec();
+            page_query.push(("offset", offset.to_string()));
+            let remaining = limit - items.len() as u32;
+            page_query.push(("limit", remaining.min(page_size).to_string()));
+            let text = self.get(path, &page_query).await?;
+            let page = parse(&text)?;
+            let next_offset = page.next_offset();
+            items.append(&mut page.into_items());
+            match next_offset {
+                Some(next_offset) => offset = next_offset,
+                None => break,
+            }
+        }
+
+        items.truncate(limit as usize);
+        Ok(items)
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<String> {
+        let request_builder = self.request(Method::GET, path)?.query(query);
+        let res = self.send(request_builder, StatusCode::OK).await?;
+        Ok(res.text().await?)
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let url = self.url.join(path)?;
+        let request_builder = self.client.request(method, url);
+        Ok(match &self.credentials {
+            Credentials::None => request_builder,
+            Credentials::ApiKey(api_key) => {
+                request_builder.header(API_KEY_HEADER, api_key.as_str())
+            }
+            Credentials::Basic { login, password } => {
+                request_builder.basic_auth(login, Some(password))
+            }
+        })
+    }
+
+    async fn send(
+        &self,
+        request_builder: RequestBuilder,
+        expected: StatusCode,
+    ) -> Result<Response> {
+        let res = request_builder.send().await?;
+        if res.status() == expected {
+            Ok(res)
+        } else {
+            Err(failure(res).await)
+        }
+    }
+
+    /// Sends a request changing or deleting an object. Redmine answers it
+    /// with no content, which is 204 since version 4 and 200 before.
+    async fn send_update(&self, request_builder: RequestBuilder) -> Result<()> {
+        let res = request_builder.send().await?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(failure(res).await)
+        }
     }
 }
 
-pub async fn time(
-    url: &str,
-    api_key: &Option<String>,
-    range: &TimeRange,
-) -> Result<Vec<response::TimeEntry>> {
-    let from = range.from.format(DATE_FORMAT).to_string();
-    let to = range.to.format(DATE_FORMAT).to_string();
-    let url = format!(
-        "{}/time_entries.json?user_id=me&from={}&to={}",
-        url, from, to
-    );
-
-    let client = Client::new();
-    let mut request_builder = client.get(&url);
-    if let Some(api_key) = api_key {
-        request_builder = request_builder.header("X-Redmine-API-Key", api_key.clone());
-    }
-    let res = request_builder.send().await?;
+/// Error reported for an unexpected response.
+async fn failure(res: Response) -> Error {
     let status = res.status();
-    if status == reqwest::StatusCode::OK {
-        response::parse_time_entries(&res.text().await?)
-    } else {
-        Err(Error::RequestFailed(status))
+    match status {
+        StatusCode::UNAUTHORIZED => Error::Unauthorized,
+        StatusCode::FORBIDDEN => Error::Forbidden,
+        StatusCode::NOT_FOUND => Error::NotFound,
+        // besides validation errors (422), plugins may explain other client errors
+        _ if status.is_client_error() => match res.text().await {
+            Ok(text) => match response::parse_errors(&text) {
+                Ok(errors) if !errors.is_empty() => Error::Rejected(errors),
+                _ => Error::RequestFailed(status),
+            },
+            Err(error) => error.into(),
+        },
+        _ => Error::RequestFailed(status),
     }
 }
 
-pub async fn activities(
+/// Where the password for [`login`] is read from.
+pub enum PasswordSource {
+    /// Prompt on the terminal without echoing the input.
+    Terminal,
+    /// Read a single line from the standard input.
+    Stdin,
+}
+
+/// Asks for the login name (unless given) and password and fetches
+/// the user details, including the API key.
+pub async fn login(
     url: &str,
-    api_key: &Option<String>,
-) -> Result<Vec<response::TimeEntryActivity>> {
-    let url = format!("{}/enumerations/time_entry_activities.json", url);
-    let client = Client::new();
-    let mut request_builder = client.get(&url);
-    if let Some(api_key) = api_key {
-        request_builder = request_builder.header("X-Redmine-API-Key", api_key.clone());
-    }
-    let res = request_builder.send().await?;
-    let status = res.status();
-    if status == reqwest::StatusCode::OK {
-        response::parse_time_entry_activities(&res.text().await?)
+    login_name: Option<String>,
+    password_source: PasswordSource,
+) -> Result<response::User> {
+    let login = if let Some(name) = login_name {
+        name
     } else {
-        Err(Error::RequestFailed(status))
+        print!("Login: ");
+        io::stdout().flush()?;
+        read_line()?
+    };
+
+    let password = match password_source {
+        PasswordSource::Terminal => {
+            let password = read_password_from_tty(Some("Password: "))?;
+            println!();
+            password
+        }
+        PasswordSource::Stdin => read_line()?,
+    };
+
+    RedmineClient::new(url, Credentials::Basic { login, password })?
+        .current_user()
+        .await
+}
+
+/// Checks that the API key is accepted by the server and fetches
+/// the details of the user it belongs to.
+pub async fn login_with_api_key(url: &str, api_key: String) -> Result<response::User> {
+    RedmineClient::new(url, Credentials::ApiKey(api_key))?
+        .current_user()
+        .await
+}
+
+fn read_line() -> Result<String> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    line.retain(|c| c != '\n' && c != '\r');
+    Ok(line)
+}
+
+/// Criteria of the issue list. Fields set to `None` are not filtered on.
+#[derive(Default)]
+pub struct IssueFilter {
+    /// Project id or identifier.
+    pub project: Option<String>,
+    /// User id or `me`.
+    pub assignee: Option<String>,
+    /// `open`, `closed`, `all` or a status id.
+    pub status: Option<String>,
+    pub tracker_id: Option<i32>,
+    pub version_id: Option<i32>,
+    pub priority_id: Option<i32>,
+    /// Text the subject has to contain.
+    pub subject: Option<String>,
+    /// Redmine sort order, e.g. `priority:desc,updated_on`.
+    pub sort: Option<String>,
+}
+
+impl IssueFilter {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if let Some(project) = &self.project {
+            query.push(("project_id", project.clone()));
+        }
+        if let Some(assignee) = &self.assignee {
+            query.push(("assigned_to_id", assignee.clone()));
+        }
+        if let Some(status) = &self.status {
+            let status = if status == "all" { "*" } else { status };
+            query.push(("status_id", status.to_string()));
+        }
+        if let Some(tracker_id) = self.tracker_id {
+            query.push(("tracker_id", tracker_id.to_string()));
+        }
+        if let Some(version_id) = self.version_id {
+            query.push(("fixed_version_id", version_id.to_string()));
+        }
+        if let Some(priority_id) = self.priority_id {
+            query.push(("priority_id", priority_id.to_string()));
+        }
+        if let Some(subject) = &self.subject {
+            query.push(("subject", format!("~{}", subject)));
+        }
+        if let Some(sort) = &self.sort {
+            query.push(("sort", sort.clone()));
+        }
+        query
     }
 }
 
+/// Body of the issue creation request.
+#[derive(Serialize, Deserialize)]
+pub struct IssueRequest {
+    pub issue: Issue,
+}
+
+/// An issue to be created.
+#[derive(Serialize, Deserialize)]
+pub struct Issue {
+    /// Project id or identifier.
+    pub project_id: String,
+    pub tracker_id: i32,
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assigned_to_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_issue_id: Option<i32>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "redmine_optional_date_format"
+    )]
+    pub start_date: Option<NaiveDate>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "redmine_optional_date_format"
+    )]
+    pub due_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_hours: Option<f32>,
+}
+
+/// Body of the issue update request.
+#[derive(Serialize, Deserialize)]
+pub struct IssueUpdateRequest {
+    pub issue: IssueUpdate,
+}
+
+/// Changes to an existing issue. Fields set to `None` are left intact.
+#[derive(Default, Serialize, Deserialize)]
+pub struct IssueUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assigned_to_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_ratio: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_version_id: Option<i32>,
+    /// Comment added to the issue history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Body of the time entry creation request.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntryRequest {
     pub time_entry: TimeEntry,
 }
 
+/// A time entry to be logged.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntry {
     pub issue_id: i32,
@@ -116,19 +492,115 @@ pub struct TimeEntry {
     pub comments: Option<String>,
 }
 
-pub async fn time_add(url: &str, api_key: &Option<String>, time_entry: TimeEntry) -> Result<()> {
-    let url = format!("{}/time_entries.json", url);
-    let time_entry_request = TimeEntryRequest { time_entry };
-    let client = Client::new();
-    let mut request_builder = client.post(&url).json(&time_entry_request);
-    if let Some(api_key) = api_key {
-        request_builder = request_builder.header("X-Redmine-API-Key", api_key.clone());
+/// Body of the time entry update request.
+#[derive(Serialize, Deserialize)]
+pub struct TimeEntryUpdateRequest {
+    pub time_entry: TimeEntryUpdate,
+}
+
+/// Changes to an existing time entry. Fields set to `None` are left intact.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TimeEntryUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_id: Option<i32>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "redmine_optional_date_format"
+    )]
+    pub spent_on: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hours: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_issue_without_optional_fields() {
+        let issue = Issue {
+            project_id: "website".to_string(),
+            tracker_id: 1,
+            subject: "Login fails".to_string(),
+            description: None,
+            status_id: None,
+            priority_id: Some(3),
+            assigned_to_id: None,
+            parent_issue_id: None,
+            start_date: None,
+            due_date: Some(NaiveDate::from_ymd(2019, 8, 23)),
+            estimated_hours: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&issue).unwrap(),
+            r#"{"project_id":"website","tracker_id":1,"subject":"Login fails","priority_id":3,"due_date":"2019-08-23"}"#
+        );
     }
-    let res = request_builder.send().await?;
-    let status = res.status();
-    if status == reqwest::StatusCode::CREATED {
-        Ok(())
-    } else {
-        Err(Error::RequestFailed(status))
+
+    #[test]
+    fn issue_update_with_note_only() {
+        let update = IssueUpdate {
+            notes: Some("Fixed".to_string()),
+            ..IssueUpdate::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&update).unwrap(),
+            r#"{"notes":"Fixed"}"#
+        );
+    }
+
+    #[test]
+    fn time_entry_update_with_set_fields_only() {
+        let update = TimeEntryUpdate {
+            spent_on: Some(NaiveDate::from_ymd(2019, 8, 21)),
+            hours: Some(1.5),
+            ..TimeEntryUpdate::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&update).unwrap(),
+            r#"{"spent_on":"2019-08-21","hours":1.5}"#
+        );
+    }
+
+    #[test]
+    fn empty_time_entry_update() {
+        assert_eq!(
+            serde_json::to_string(&TimeEntryUpdate::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn empty_issue_filter() {
+        assert!(IssueFilter::default().query().is_empty());
+    }
+
+    #[test]
+    fn issue_filter_query() {
+        let filter = IssueFilter {
+            project: Some("website".to_string()),
+            assignee: Some("me".to_string()),
+            status: Some("all".to_string()),
+            tracker_id: Some(2),
+            subject: Some("login".to_string()),
+            sort: Some("priority:desc".to_string()),
+            ..IssueFilter::default()
+        };
+        assert_eq!(
+            filter.query(),
+            vec![
+                ("project_id", "website".to_string()),
+                ("assigned_to_id", "me".to_string()),
+                ("status_id", "*".to_string()),
+                ("tracker_id", "2".to_string()),
+                ("subject", "~login".to_string()),
+                ("sort", "priority:desc".to_string()),
+            ]
+        );
     }
 }
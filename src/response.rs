@@ -4,6 +4,20 @@ use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json;
 
+/// Error messages returned by Redmine when it rejects a request,
+/// e.g. `{"errors": ["Hours is invalid"]}`.
+#[derive(Serialize, Deserialize)]
+pub struct ErrorsResponse {
+    pub errors: Vec<String>,
+}
+
+/// Parses the body of a failed request.
+pub fn parse_errors(text: &str) -> Result<Vec<String>> {
+    let response: ErrorsResponse = serde_json::from_str(text)?;
+    Ok(response.errors)
+}
+
+/// Redmine user account.
 #[derive(Serialize, Deserialize)]
 pub struct User {
     pub id: i32,
@@ -21,11 +35,13 @@ struct UserResponse {
     user: User,
 }
 
+/// Parses the body of a `/users/current.json` response.
 pub fn parse_user(text: &str) -> Result<User> {
     let user_response: UserResponse = serde_json::from_str(text)?;
     Ok(user_response.user)
 }
 
+/// Time entry as returned by Redmine.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntry {
     pub id: i32,
@@ -43,45 +59,370 @@ pub struct TimeEntry {
     pub updated_on: NaiveDateTime,
 }
 
+/// Project a time entry was logged on.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntryProject {
     pub id: i32,
     pub name: String,
 }
 
+/// Issue a time entry was logged on.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntryIssue {
     pub id: i32,
 }
 
+/// Time entry activity, e.g. "Development".
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimeEntryActivity {
     pub id: i32,
     pub name: String,
 }
 
+/// User who logged a time entry.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntryUser {
     pub id: i32,
     pub name: String,
 }
 
+/// One page of time entries.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntriesResponse {
     pub time_entries: Vec<TimeEntry>,
+    pub total_count: u32,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// One page of a list that Redmine returns in parts.
+pub trait Page {
+    type Item;
+
+    /// Offset of the next page, or `None` if this is the last one.
+    fn next_offset(&self) -> Option<u32>;
+    fn into_items(self) -> Vec<Self::Item>;
 }
 
-pub fn parse_time_entries(text: &str) -> Result<Vec<TimeEntry>> {
+impl Page for TimeEntriesResponse {
+    type Item = TimeEntry;
+
+    fn next_offset(&self) -> Option<u32> {
+        next_offset(self.offset, self.time_entries.len(), self.total_count)
+    }
+
+    fn into_items(self) -> Vec<TimeEntry> {
+        self.time_entries
+    }
+}
+
+fn next_offset(offset: u32, count: usize, total_count: u32) -> Option<u32> {
+    let next_offset = offset + count as u32;
+    if count == 0 || next_offset >= total_count {
+        None
+    } else {
+        Some(next_offset)
+    }
+}
+
+/// Parses the body of a `/time_entries.json` response.
+pub fn parse_time_entries(text: &str) -> Result<TimeEntriesResponse> {
     let response: TimeEntriesResponse = serde_json::from_str(text)?;
-    Ok(response.time_entries)
+    Ok(response)
 }
 
+/// Single time entry.
 #[derive(Serialize, Deserialize)]
 pub struct TimeEntryResponse {
+    pub time_entry: TimeEntry,
+}
+
+/// Parses the body of a `/time_entries/<id>.json` response.
+pub fn parse_time_entry(text: &str) -> Result<TimeEntry> {
+    let response: TimeEntryResponse = serde_json::from_str(text)?;
+    Ok(response.time_entry)
+}
+
+/// List of time entry activities.
+#[derive(Serialize, Deserialize)]
+pub struct TimeEntryActivitiesResponse {
     pub time_entry_activities: Vec<TimeEntryActivity>,
 }
 
+/// Parses the body of a `/enumerations/time_entry_activities.json` response.
 pub fn parse_time_entry_activities(text: &str) -> Result<Vec<TimeEntryActivity>> {
-    let response: TimeEntryResponse = serde_json::from_str(text)?;
+    let response: TimeEntryActivitiesResponse = serde_json::from_str(text)?;
     Ok(response.time_entry_activities)
 }
+
+/// Object referenced by its id and name, e.g. the project or status of an issue.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reference {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Parent of a subtask.
+#[derive(Serialize, Deserialize)]
+pub struct IssueParent {
+    pub id: i32,
+}
+
+/// Issue as returned by Redmine.
+#[derive(Serialize, Deserialize)]
+pub struct Issue {
+    pub id: i32,
+    pub project: Reference,
+    pub tracker: Reference,
+    pub status: Reference,
+    pub priority: Reference,
+    pub author: Reference,
+    #[serde(default)]
+    pub assigned_to: Option<Reference>,
+    #[serde(default)]
+    pub fixed_version: Option<Reference>,
+    #[serde(default)]
+    pub parent: Option<IssueParent>,
+    pub subject: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, with = "redmine_optional_date_format")]
+    pub start_date: Option<NaiveDate>,
+    #[serde(default, with = "redmine_optional_date_format")]
+    pub due_date: Option<NaiveDate>,
+    pub done_ratio: u32,
+    #[serde(default)]
+    pub estimated_hours: Option<f32>,
+    /// Only returned for a single issue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spent_hours: Option<f32>,
+    #[serde(with = "redmine_datetime_format")]
+    pub created_on: NaiveDateTime,
+    #[serde(with = "redmine_datetime_format")]
+    pub updated_on: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub journals: Vec<Journal>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<IssueChild>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations: Vec<IssueRelation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watchers: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// Comment and/or set of changes made to an issue at once.
+#[derive(Serialize, Deserialize)]
+pub struct Journal {
+    pub id: i32,
+    pub user: Reference,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(with = "redmine_datetime_format")]
+    pub created_on: NaiveDateTime,
+    #[serde(default)]
+    pub details: Vec<JournalDetail>,
+}
+
+/// Change of a single issue field, e.g. property `attr` and name `status_id`.
+#[derive(Serialize, Deserialize)]
+pub struct JournalDetail {
+    pub property: String,
+    pub name: String,
+    #[serde(default)]
+    pub old_value: Option<String>,
+    #[serde(default)]
+    pub new_value: Option<String>,
+}
+
+/// Subtask of an issue.
+#[derive(Serialize, Deserialize)]
+pub struct IssueChild {
+    pub id: i32,
+    pub tracker: Reference,
+    pub subject: String,
+}
+
+/// Relation between two issues, e.g. `relates` or `blocks`.
+#[derive(Serialize, Deserialize)]
+pub struct IssueRelation {
+    pub id: i32,
+    pub issue_id: i32,
+    pub issue_to_id: i32,
+    pub relation_type: String,
+}
+
+/// File attached to an issue.
+#[derive(Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i32,
+    pub filename: String,
+    pub filesize: u64,
+    pub author: Reference,
+    #[serde(with = "redmine_datetime_format")]
+    pub created_on: NaiveDateTime,
+}
+
+/// One page of issues.
+#[derive(Serialize, Deserialize)]
+pub struct IssuesResponse {
+    pub issues: Vec<Issue>,
+    pub total_count: u32,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Page for IssuesResponse {
+    type Item = Issue;
+
+    fn next_offset(&self) -> Option<u32> {
+        next_offset(self.offset, self.issues.len(), self.total_count)
+    }
+
+    fn into_items(self) -> Vec<Issue> {
+        self.issues
+    }
+}
+
+/// Parses the body of an `/issues.json` response.
+pub fn parse_issues(text: &str) -> Result<IssuesResponse> {
+    let response: IssuesResponse = serde_json::from_str(text)?;
+    Ok(response)
+}
+
+/// Single issue.
+#[derive(Serialize, Deserialize)]
+pub struct IssueResponse {
+    pub issue: Issue,
+}
+
+/// Parses the body of an `/issues/<id>.json` response.
+pub fn parse_issue(text: &str) -> Result<Issue> {
+    let response: IssueResponse = serde_json::from_str(text)?;
+    Ok(response.issue)
+}
+
+/// List of trackers, e.g. "Bug" or "Feature".
+#[derive(Serialize, Deserialize)]
+pub struct TrackersResponse {
+    pub trackers: Vec<Reference>,
+}
+
+/// Parses the body of a `/trackers.json` response.
+pub fn parse_trackers(text: &str) -> Result<Vec<Reference>> {
+    let response: TrackersResponse = serde_json::from_str(text)?;
+    Ok(response.trackers)
+}
+
+/// List of issue statuses, e.g. "New" or "Closed".
+#[derive(Serialize, Deserialize)]
+pub struct IssueStatusesResponse {
+    pub issue_statuses: Vec<Reference>,
+}
+
+/// Parses the body of an `/issue_statuses.json` response.
+pub fn parse_issue_statuses(text: &str) -> Result<Vec<Reference>> {
+    let response: IssueStatusesResponse = serde_json::from_str(text)?;
+    Ok(response.issue_statuses)
+}
+
+/// List of issue priorities, e.g. "Normal" or "Urgent".
+#[derive(Serialize, Deserialize)]
+pub struct IssuePrioritiesResponse {
+    pub issue_priorities: Vec<Reference>,
+}
+
+/// Parses the body of an `/enumerations/issue_priorities.json` response.
+pub fn parse_issue_priorities(text: &str) -> Result<Vec<Reference>> {
+    let response: IssuePrioritiesResponse = serde_json::from_str(text)?;
+    Ok(response.issue_priorities)
+}
+
+/// Redmine project.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+    pub identifier: String,
+}
+
+/// One page of projects.
+#[derive(Serialize, Deserialize)]
+pub struct ProjectsResponse {
+    pub projects: Vec<Project>,
+    pub total_count: u32,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Page for ProjectsResponse {
+    type Item = Project;
+
+    fn next_offset(&self) -> Option<u32> {
+        next_offset(self.offset, self.projects.len(), self.total_count)
+    }
+
+    fn into_items(self) -> Vec<Project> {
+        self.projects
+    }
+}
+
+/// Parses the body of a `/projects.json` response.
+pub fn parse_projects(text: &str) -> Result<ProjectsResponse> {
+    let response: ProjectsResponse = serde_json::from_str(text)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors_array() {
+        assert_eq!(
+            parse_errors(r#"{"errors": ["Hours is invalid", "Activity cannot be blank"]}"#)
+                .unwrap(),
+            vec!["Hours is invalid", "Activity cannot be blank"]
+        );
+    }
+
+    #[test]
+    fn parse_errors_empty() {
+        assert!(parse_errors(r#"{"errors": []}"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_errors_not_json() {
+        assert!(parse_errors("<html><body>Conflict</body></html>").is_err());
+        assert!(parse_errors("").is_err());
+        assert!(parse_errors(r#"{"error": "Conflict"}"#).is_err());
+    }
+
+    #[test]
+    fn next_offset_no_entries() {
+        assert_eq!(next_offset(0, 0, 0), None);
+    }
+
+    #[test]
+    fn next_offset_full_page() {
+        assert_eq!(next_offset(0, 25, 60), Some(25));
+        assert_eq!(next_offset(25, 25, 60), Some(50));
+    }
+
+    #[test]
+    fn next_offset_partial_last_page() {
+        assert_eq!(next_offset(50, 10, 60), None);
+    }
+
+    #[test]
+    fn next_offset_total_multiple_of_page_size() {
+        assert_eq!(next_offset(25, 25, 50), None);
+    }
+
+    #[test]
+    fn next_offset_empty_page_before_total() {
+        // entries deleted while paging must not cause an endless loop
+        assert_eq!(next_offset(25, 0, 50), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::Error;
 use std::result;
 
+/// Result type returned throughout the crate.
 pub type Result<T> = result::Result<T, Error>;
@@ -21,6 +21,33 @@ pub mod redmine_date_format {
     }
 }
 
+pub mod redmine_optional_date_format {
+    use super::redmine_date_format;
+    use chrono::NaiveDate;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "redmine_date_format")] NaiveDate);
+
+    pub fn serialize<S>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => redmine_date_format::serialize(date, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let wrapper: Option<Wrapper> = Option::deserialize(deserializer)?;
+        Ok(wrapper.map(|Wrapper(date)| date))
+    }
+}
+
 pub mod redmine_datetime_format {
     use crate::constants::DATE_TIME_FORMAT;
     use chrono::NaiveDateTime;
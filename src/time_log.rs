@@ -1,14 +1,88 @@
-use crate::error::Error::{InvalidIssueId, InvalidTimeLogHours};
+use crate::error::Error::{InvalidIssueId, InvalidTimeLogHours, TimeLogHoursOutOfRange};
 use crate::result::Result;
 
+const MAX_HOURS: f32 = 24.0;
+const HOUR_UNITS: &[&str] = &["", "h", "hr", "hrs", "hour", "hours"];
+const MINUTE_UNITS: &[&str] = &["m", "min", "mins", "minute", "minutes"];
+
+/// Parses a number of hours, e.g. `5`, `5.5`, `5,5`, `6h`, `1h30m`, `1:45`,
+/// `90m` or `45min`.
+pub fn parse_duration(input: &str) -> Result<f32> {
+    let normalized = input.trim().to_lowercase().replace(',', ".");
+    let (sign, duration) = match normalized.strip_prefix('-') {
+        Some(duration) => (-1.0, duration),
+        None => (1.0, normalized.as_str()),
+    };
+    let hours = match duration.find(':') {
+        Some(colon) => parse_clock(&duration[..colon], &duration[colon + 1..]),
+        None => parse_units(duration),
+    }
+    .ok_or_else(|| InvalidTimeLogHours(input.to_owned()))?;
+    Ok(hours * sign)
+}
+
+/// Parses hours of a time entry like `parse_duration`. The result has to be
+/// more than 0 and at most 24 hours.
 pub fn parse_hours(input: &str) -> Result<f32> {
-    let suffixes: &[_] = &[' ', 'h', 'H'];
-    let trimmed_input = input.trim_start().trim_end_matches(suffixes);
-    trimmed_input
-        .parse()
-        .map_err(|_| InvalidTimeLogHours(input.to_owned()))
+    let hours = parse_duration(input)?;
+    if hours <= 0.0 || hours > MAX_HOURS {
+        return Err(TimeLogHoursOutOfRange(input.to_owned()));
+    }
+    Ok(hours)
 }
 
+/// Parses `hours:minutes`, e.g. `1:45`.
+fn parse_clock(hours: &str, minutes: &str) -> Option<f32> {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !is_number(hours) || !is_number(minutes) || minutes.len() != 2 {
+        return None;
+    }
+    let hours: f32 = hours.parse().ok()?;
+    let minutes: f32 = minutes.parse().ok()?;
+    if minutes < 60.0 {
+        Some(hours + minutes / 60.0)
+    } else {
+        None
+    }
+}
+
+/// Parses numbers followed by units, e.g. `1.5`, `6h`, `1h 30m` or `90 min`.
+/// Whitespace is allowed around units but not inside numbers.
+fn parse_units(input: &str) -> Option<f32> {
+    let mut hours = None;
+    let mut minutes = None;
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, after_number) = rest.split_at(number_end);
+        let unit_end = after_number
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(after_number.len());
+        let (unit, after_unit) = after_number.split_at(unit_end);
+        let unit = unit.trim();
+        let value: f32 = number.parse().ok()?;
+
+        if HOUR_UNITS.contains(&unit) && hours.is_none() && minutes.is_none() {
+            hours = Some(value);
+        } else if MINUTE_UNITS.contains(&unit) && minutes.is_none() {
+            minutes = Some(value);
+        } else {
+            return None;
+        }
+        rest = after_unit;
+    }
+
+    match (hours, minutes) {
+        (Some(_), Some(minutes)) if minutes >= 60.0 => None,
+        (None, None) => None,
+        (hours, minutes) => Some(hours.unwrap_or(0.0) + minutes.unwrap_or(0.0) / 60.0),
+    }
+}
+
+/// Parses an issue id, with or without the `#` prefix.
 pub fn parse_issue(input: &str) -> Result<i32> {
     let prefixes: &[_] = &[' ', '#'];
     let trimmed_input = input.trim_start_matches(prefixes).trim_end();
@@ -76,6 +150,79 @@ mod tests {
         assert!(parse_hours("h").is_err());
     }
 
+    #[test]
+    fn parse_hours_comma_fraction() {
+        assert!((parse_hours("1,5").unwrap() - 1.5).abs() < 0.001);
+        assert!((parse_hours("2,25h").unwrap() - 2.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_hours_hours_and_minutes() {
+        assert!((parse_hours("1h30m").unwrap() - 1.5).abs() < 0.001);
+        assert!((parse_hours("1h 15min").unwrap() - 1.25).abs() < 0.001);
+        assert!((parse_hours("2H45M").unwrap() - 2.75).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_hours_clock() {
+        assert!((parse_hours("1:30").unwrap() - 1.5).abs() < 0.001);
+        assert!((parse_hours("1:45").unwrap() - 1.75).abs() < 0.001);
+        assert!((parse_hours("0:20").unwrap() - 1.0 / 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_hours_invalid_clock() {
+        assert!(parse_hours("1:60").is_err());
+        assert!(parse_hours("1:5").is_err());
+        assert!(parse_hours(":30").is_err());
+        assert!(parse_hours("1:30:00").is_err());
+    }
+
+    #[test]
+    fn parse_hours_minutes() {
+        assert!((parse_hours("90m").unwrap() - 1.5).abs() < 0.001);
+        assert!((parse_hours("45min").unwrap() - 0.75).abs() < 0.001);
+        assert!((parse_hours("30 mins").unwrap() - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_hours_invalid_units() {
+        assert!(parse_hours("30m1h").is_err());
+        assert!(parse_hours("1h2h").is_err());
+        assert!(parse_hours("1h90m").is_err());
+        assert!(parse_hours("1h30").is_err());
+        assert!(parse_hours("5d").is_err());
+        assert!(parse_hours("1.2.3").is_err());
+    }
+
+    #[test]
+    fn parse_hours_out_of_range() {
+        match parse_hours("-1") {
+            Err(TimeLogHoursOutOfRange(input)) => assert_eq!(input, "-1"),
+            _ => panic!("negative hours accepted"),
+        }
+        assert!(parse_hours("0").is_err());
+        assert!(parse_hours("0h0m").is_err());
+        assert!(parse_hours("24.5").is_err());
+        assert!(parse_hours("25:00").is_err());
+        assert!((parse_hours("24h").unwrap() - 24.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_hours_space_inside_number() {
+        assert!(parse_hours("1 5").is_err());
+        assert!(parse_hours("2 0").is_err());
+        assert!(parse_hours("1.5 2").is_err());
+        assert!(parse_hours("1. 5h").is_err());
+    }
+
+    #[test]
+    fn parse_duration_more_than_a_day() {
+        assert!((parse_duration("40").unwrap() - 40.0).abs() < 0.001);
+        assert!((parse_duration("36h 30m").unwrap() - 36.5).abs() < 0.001);
+        assert!(parse_duration("4 0").is_err());
+    }
+
     #[test]
     fn parse_issue_id() {
         assert_eq!(parse_issue("12345").unwrap(), 12345);
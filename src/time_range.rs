@@ -1,20 +1,51 @@
 use crate::error::Error;
 use crate::result::Result;
-use chrono::{Datelike, Duration, Local, NaiveDate};
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+use std::convert::TryFrom;
 
+/// An inclusive range of dates.
 #[derive(Debug, PartialEq)]
 pub struct TimeRange {
     pub from: NaiveDate,
     pub to: NaiveDate,
 }
 
+/// Settings affecting how time ranges are resolved to dates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeSettings {
+    /// First day of week. ISO weeks such as `2019-W34` always start on Monday.
+    pub week_start: Weekday,
+    /// Start of ranges with the beginning left out, e.g. `..2019-05-09`.
+    pub epoch: NaiveDate,
+}
+
+impl Default for RangeSettings {
+    fn default() -> Self {
+        RangeSettings {
+            week_start: Weekday::Mon,
+            epoch: NaiveDate::from_ymd(1970, 1, 1),
+        }
+    }
+}
+
 const RANGE_SEPARATOR: &str = "..";
 
 impl TimeRange {
+    /// Parses a range such as `today`, `week`, `month-1`, `last-fri`, `last-7d`, `2019-W34`,
+    /// `2019-01-23..2019-05-09`, `2019-01-23..` or `month-1..week-1` relative
+    /// to the current local date, using the default settings.
     pub fn parse(input: &str) -> Result<TimeRange> {
+        TimeRange::parse_with_settings(input, &RangeSettings::default())
+    }
+
+    /// Same as [`TimeRange::parse`], but with the given settings.
+    pub fn parse_with_settings(input: &str, settings: &RangeSettings) -> Result<TimeRange> {
         let range = TimePointRange::parse(input)?;
-        let from = range.from.to_lower_bound()?;
-        let to = range.to.to_upper_bound()?;
+        let from = range.from.to_lower_bound(settings)?;
+        let to = range.to.to_upper_bound(settings)?;
+        if from > to {
+            return Err(Error::ReversedTimeRange(from, to));
+        }
         Ok(TimeRange { from, to })
     }
 }
@@ -22,102 +53,259 @@ impl TimeRange {
 #[derive(Clone, Debug, PartialEq)]
 enum TimePoint {
     Date(NaiveDate),
+    Day(i32),
     Week(i32),
+    /// Day of the current week, which may still be ahead, e.g. `fri` on a Monday.
+    Weekday(Weekday),
+    /// The most recent day before today falling on the weekday, e.g. `last-fri`.
+    LastWeekday(Weekday),
+    /// ISO 8601 week of a year.
+    IsoWeek(i32, u32),
     Month(i32),
+    Quarter(i32),
+    Year(i32),
+    /// Given number of days, weeks or months up to today, e.g. `last-7d`.
+    Last(u32, Period),
+    /// The configured start of open-ended ranges.
+    Epoch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Period {
+    Day,
+    Week,
+    Month,
 }
 
 impl TimePoint {
-    fn to_lower_bound(&self) -> Result<NaiveDate> {
-        self.to_lower_bound_with_date(Local::today().naive_local())
+    fn to_lower_bound(&self, settings: &RangeSettings) -> Result<NaiveDate> {
+        self.to_lower_bound_with_settings(Local::today().naive_local(), settings)
     }
 
+    #[cfg(test)]
     fn to_lower_bound_with_date(&self, today: NaiveDate) -> Result<NaiveDate> {
+        self.to_lower_bound_with_settings(today, &RangeSettings::default())
+    }
+
+    fn to_lower_bound_with_settings(
+        &self,
+        today: NaiveDate,
+        settings: &RangeSettings,
+    ) -> Result<NaiveDate> {
+        let week_start = settings.week_start;
         match *self {
             TimePoint::Date(date) => Ok(date),
-            TimePoint::Week(offset) => Ok(today
-                - Duration::days(today.weekday().num_days_from_monday() as i64)
-                + Duration::weeks(offset as i64)),
-            TimePoint::Month(offset) => {
-                let year_offset = offset / 12;
-                let month_offset = offset % 12;
-                let date = today
-                    .with_year(today.year() + year_offset)
-                    .ok_or(Error::InvalidMonthOffset(offset))?;
-                let date = date
-                    .with_month((date.month() as i32 + month_offset) as u32)
-                    .ok_or(Error::InvalidMonthOffset(offset))?;
-                Ok(date
-                    .with_day(1)
-                    .expect("Cannot use '1' as day of month (?)"))
+            TimePoint::Day(offset) => add_days(today, offset as i64),
+            TimePoint::Week(offset) => {
+                add_days(week_start_date(today, week_start), 7 * offset as i64)
+            }
+            TimePoint::Weekday(weekday) => weekday_date(today, weekday, week_start),
+            TimePoint::LastWeekday(weekday) => last_weekday_date(today, weekday),
+            TimePoint::IsoWeek(year, week) => iso_week_date(year, week, Weekday::Mon),
+            TimePoint::Month(offset) => month_start(today, offset as i64),
+            TimePoint::Quarter(offset) => quarter_start(today, offset),
+            TimePoint::Year(offset) => year_start(today, offset),
+            TimePoint::Last(count, Period::Day) => add_days(today, 1 - count as i64),
+            TimePoint::Last(count, Period::Week) => add_days(today, 1 - 7 * count as i64),
+            TimePoint::Last(count, Period::Month) => {
+                add_days(add_months(today, -(count as i64))?, 1)
             }
+            TimePoint::Epoch => Ok(settings.epoch),
         }
     }
 
-    fn to_upper_bound(&self) -> Result<NaiveDate> {
-        self.to_upper_bound_with_date(Local::today().naive_local())
+    fn to_upper_bound(&self, settings: &RangeSettings) -> Result<NaiveDate> {
+        self.to_upper_bound_with_settings(Local::today().naive_local(), settings)
     }
 
+    #[cfg(test)]
     fn to_upper_bound_with_date(&self, today: NaiveDate) -> Result<NaiveDate> {
+        self.to_upper_bound_with_settings(today, &RangeSettings::default())
+    }
+
+    fn to_upper_bound_with_settings(
+        &self,
+        today: NaiveDate,
+        settings: &RangeSettings,
+    ) -> Result<NaiveDate> {
+        let week_start = settings.week_start;
         match *self {
             TimePoint::Date(date) => Ok(date),
-            TimePoint::Week(offset) => Ok(today
-                + Duration::days(6 - today.weekday().num_days_from_monday() as i64)
-                + Duration::weeks(offset as i64)),
-            TimePoint::Month(offset) => {
-                let year_offset = offset / 12;
-                let month_offset = offset % 12;
-                let date = today
-                    .with_year(today.year() + year_offset)
-                    .ok_or(Error::InvalidMonthOffset(offset))?;
-                let date = date
-                    .with_month((date.month() as i32 + month_offset) as u32)
-                    .ok_or(Error::InvalidMonthOffset(offset))?;
-                let date = date
-                    .with_day(1)
-                    .expect("Cannot use '1' as day of month (?)");
-                if date.month() < 12 {
-                    date.with_month(date.month() + 1)
-                        .map(|date| date - Duration::days(1))
-                        .ok_or(Error::InvalidMonthOffset(offset))
-                } else {
-                    Ok(date
-                        .with_month(12)
-                        .expect("Cannot use '12' as a month number (?)")
-                        .with_day(31)
-                        .expect("Cannot use '31' as day number of December (?)"))
-                }
+            TimePoint::Day(offset) => add_days(today, offset as i64),
+            TimePoint::Week(offset) => {
+                add_days(week_start_date(today, week_start), 7 * offset as i64 + 6)
+            }
+            TimePoint::Weekday(weekday) => weekday_date(today, weekday, week_start),
+            TimePoint::LastWeekday(weekday) => last_weekday_date(today, weekday),
+            TimePoint::IsoWeek(year, week) => iso_week_date(year, week, Weekday::Sun),
+            TimePoint::Month(offset) => add_days(month_start(today, offset as i64 + 1)?, -1),
+            TimePoint::Quarter(offset) => {
+                add_days(month_start(quarter_start(today, offset)?, 3)?, -1)
             }
+            TimePoint::Year(offset) => add_days(month_start(year_start(today, offset)?, 12)?, -1),
+            TimePoint::Last(_, _) => Ok(today),
+            TimePoint::Epoch => Ok(settings.epoch),
         }
     }
 }
 
-fn parse_week_month_offset(input: &str) -> Option<i32> {
-    if input.is_empty() {
-        Some(0)
-    } else if input.chars().nth(0) == Some('+') && input.len() > 1 {
-        input[1..].parse().ok()
-    } else if input.chars().nth(0) == Some('-') && input.len() > 1 {
-        input[1..].parse().ok().map(|offset: i32| -offset)
+fn add_days(date: NaiveDate, days: i64) -> Result<NaiveDate> {
+    date.checked_add_signed(Duration::days(days))
+        .ok_or(Error::DateOutOfRange)
+}
+
+/// Number of days from `week_start` to `weekday`, e.g. 1 from Sunday to Monday.
+fn days_from(week_start: Weekday, weekday: Weekday) -> i64 {
+    (weekday.num_days_from_monday() as i64 - week_start.num_days_from_monday() as i64).rem_euclid(7)
+}
+
+/// First day of the week `date` is in.
+pub fn week_start_date(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    date - Duration::days(days_from(week_start, date.weekday()))
+}
+
+fn weekday_date(today: NaiveDate, weekday: Weekday, week_start: Weekday) -> Result<NaiveDate> {
+    add_days(
+        week_start_date(today, week_start),
+        days_from(week_start, weekday),
+    )
+}
+
+/// The latest date before `today` falling on `weekday`, a week ago if today is that weekday.
+fn last_weekday_date(today: NaiveDate, weekday: Weekday) -> Result<NaiveDate> {
+    let days = match days_from(weekday, today.weekday()) {
+        0 => 7,
+        days => days,
+    };
+    add_days(today, -days)
+}
+
+fn iso_week_date(year: i32, week: u32, weekday: Weekday) -> Result<NaiveDate> {
+    NaiveDate::from_isoywd_opt(year, week, weekday)
+        .ok_or_else(|| Error::InvalidTimeRangeFormat(format!("{}-W{:02}", year, week)))
+}
+
+/// First day of the month `offset` months away from the one `date` is in,
+/// carrying over to other years as needed.
+fn month_start(date: NaiveDate, offset: i64) -> Result<NaiveDate> {
+    let months = date.year() as i64 * 12 + date.month0() as i64 + offset;
+    let year = i32::try_from(months.div_euclid(12)).map_err(|_| Error::DateOutOfRange)?;
+    let month = months.rem_euclid(12) as u32 + 1;
+    NaiveDate::from_ymd_opt(year, month, 1).ok_or(Error::DateOutOfRange)
+}
+
+/// The same day `offset` months away from `date`, or the last day
+/// of that month if it is shorter, e.g. February 28th for March 31st minus one month.
+fn add_months(date: NaiveDate, offset: i64) -> Result<NaiveDate> {
+    let start = month_start(date, offset)?;
+    let last_day = add_days(month_start(date, offset + 1)?, -1)?.day();
+    Ok(start
+        .with_day(date.day().min(last_day))
+        .expect("Cannot use a day within the month (?)"))
+}
+
+/// First day of the quarter `offset` quarters away from the one `date` is in.
+fn quarter_start(date: NaiveDate, offset: i32) -> Result<NaiveDate> {
+    month_start(date, offset as i64 * 3 - (date.month0() % 3) as i64)
+}
+
+/// First day of the year `offset` years away from the one `date` is in.
+fn year_start(date: NaiveDate, offset: i32) -> Result<NaiveDate> {
+    month_start(date, offset as i64 * 12 - date.month0() as i64)
+}
+
+/// Parses an optional offset such as `+2` or `-1` following a placeholder.
+fn parse_offset(input: &str) -> Option<i32> {
+    let (sign, number) = if input.is_empty() {
+        return Some(0);
+    } else if let Some(number) = input.strip_prefix('+') {
+        (1, number)
+    } else if let Some(number) = input.strip_prefix('-') {
+        (-1, number)
     } else {
-        None
+        return None;
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
     }
+    number.parse().ok().map(|offset: i32| sign * offset)
 }
 
-const MONTH_PLACEHOLDER: &str = "month";
+/// Parses the part of a rolling window after `last-`, e.g. `7d`, `4w` or `3-months`.
+fn parse_rolling_window(input: &str) -> Option<TimePoint> {
+    PERIOD_SUFFIXES.iter().find_map(|(suffix, period)| {
+        let count = input.strip_suffix(suffix)?;
+        if count.is_empty() || !count.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        match count.parse() {
+            Ok(count) if count > 0 => Some(TimePoint::Last(count, *period)),
+            _ => None,
+        }
+    })
+}
+
+/// Parses ISO week notation, e.g. `2019-W34`.
+fn parse_iso_week(input: &str) -> Option<TimePoint> {
+    let mut parts = input.splitn(2, ISO_WEEK_SEPARATOR);
+    let year = parts.next()?.parse().ok()?;
+    let week = parts.next()?.parse().ok()?;
+    NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)?;
+    Some(TimePoint::IsoWeek(year, week))
+}
+
+const TODAY: &str = "today";
+const YESTERDAY: &str = "yesterday";
+const DAY_PLACEHOLDER: &str = "day";
 const WEEK_PLACEHOLDER: &str = "week";
+const MONTH_PLACEHOLDER: &str = "month";
+const QUARTER_PLACEHOLDER: &str = "quarter";
+const YEAR_PLACEHOLDER: &str = "year";
+const LAST_PREFIX: &str = "last-";
+const PERIOD_SUFFIXES: &[(&str, Period)] = &[
+    ("-days", Period::Day),
+    ("-day", Period::Day),
+    ("d", Period::Day),
+    ("-weeks", Period::Week),
+    ("-week", Period::Week),
+    ("w", Period::Week),
+    ("-months", Period::Month),
+    ("-month", Period::Month),
+    ("m", Period::Month),
+];
+const ISO_WEEK_SEPARATOR: &str = "-W";
 
 fn parse_time_point(input: &str) -> Result<TimePoint> {
-    if input.starts_with(MONTH_PLACEHOLDER) {
-        parse_week_month_offset(&input[MONTH_PLACEHOLDER.len()..])
-            .ok_or_else(|| Error::InvalidTimeRangeFormat(input.to_owned()))
-            .map(TimePoint::Month)
+    let invalid = || Error::InvalidTimeRangeFormat(input.to_owned());
+    let offset = |placeholder: &str| parse_offset(&input[placeholder.len()..]).ok_or_else(invalid);
+
+    if input == TODAY {
+        Ok(TimePoint::Day(0))
+    } else if input == YESTERDAY {
+        Ok(TimePoint::Day(-1))
+    } else if input.starts_with(DAY_PLACEHOLDER) {
+        offset(DAY_PLACEHOLDER).map(TimePoint::Day)
     } else if input.starts_with(WEEK_PLACEHOLDER) {
-        parse_week_month_offset(&input[WEEK_PLACEHOLDER.len()..])
-            .ok_or_else(|| Error::InvalidTimeRangeFormat(input.to_owned()))
-            .map(TimePoint::Week)
+        offset(WEEK_PLACEHOLDER).map(TimePoint::Week)
+    } else if input.starts_with(MONTH_PLACEHOLDER) {
+        offset(MONTH_PLACEHOLDER).map(TimePoint::Month)
+    } else if input.starts_with(QUARTER_PLACEHOLDER) {
+        offset(QUARTER_PLACEHOLDER).map(TimePoint::Quarter)
+    } else if input.starts_with(YEAR_PLACEHOLDER) {
+        offset(YEAR_PLACEHOLDER).map(TimePoint::Year)
+    } else if let Ok(weekday) = input.parse::<Weekday>() {
+        Ok(TimePoint::Weekday(weekday))
+    } else if let Some(last) = input.strip_prefix(LAST_PREFIX) {
+        if let Ok(weekday) = last.parse::<Weekday>() {
+            Ok(TimePoint::LastWeekday(weekday))
+        } else {
+            parse_rolling_window(last).ok_or_else(invalid)
+        }
+    } else if let Some(iso_week) = parse_iso_week(input) {
+        Ok(iso_week)
     } else {
         NaiveDate::parse_from_str(input, "%Y-%m-%d")
-            .map_err(|_| Error::InvalidTimeRangeFormat(input.to_owned()))
+            .map_err(|_| invalid())
             .map(TimePoint::Date)
     }
 }
@@ -131,13 +319,22 @@ struct TimePointRange {
 impl TimePointRange {
     fn parse(input: &str) -> Result<TimePointRange> {
         let sep_pos = input.find(RANGE_SEPARATOR);
-        let from_end = sep_pos.unwrap_or_else(|| input.len());
+        let from_end = sep_pos.unwrap_or(input.len());
 
-        let from = parse_time_point(&input[..from_end])?;
+        let from_input = &input[..from_end];
+        let from = if from_input.is_empty() && sep_pos.is_some() {
+            TimePoint::Epoch
+        } else {
+            parse_time_point(from_input)?
+        };
 
         let to = if let Some(sep_pos) = sep_pos {
-            let to_pos = sep_pos + RANGE_SEPARATOR.len();
-            parse_time_point(&input[to_pos..])?
+            let to_input = &input[sep_pos + RANGE_SEPARATOR.len()..];
+            if to_input.is_empty() {
+                TimePoint::Day(0)
+            } else {
+                parse_time_point(to_input)?
+            }
         } else {
             from.clone()
         };
@@ -150,6 +347,13 @@ impl TimePointRange {
 mod tests {
     use super::*;
 
+    fn week_starting(week_start: Weekday) -> RangeSettings {
+        RangeSettings {
+            week_start,
+            ..RangeSettings::default()
+        }
+    }
+
     #[test]
     fn parse_time_point_current_month() {
         assert_eq!(parse_time_point("month").unwrap(), TimePoint::Month(0));
@@ -178,6 +382,131 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_time_point_today() {
+        assert_eq!(parse_time_point("today").unwrap(), TimePoint::Day(0));
+    }
+
+    #[test]
+    fn parse_time_point_yesterday() {
+        assert_eq!(parse_time_point("yesterday").unwrap(), TimePoint::Day(-1));
+    }
+
+    #[test]
+    fn parse_time_point_days_ago() {
+        assert_eq!(parse_time_point("day-3").unwrap(), TimePoint::Day(-3));
+    }
+
+    #[test]
+    fn parse_time_point_last_quarter() {
+        assert_eq!(
+            parse_time_point("quarter-1").unwrap(),
+            TimePoint::Quarter(-1)
+        );
+    }
+
+    #[test]
+    fn parse_time_point_next_year() {
+        assert_eq!(parse_time_point("year+1").unwrap(), TimePoint::Year(1));
+    }
+
+    #[test]
+    fn parse_time_point_weekday() {
+        assert_eq!(
+            parse_time_point("mon").unwrap(),
+            TimePoint::Weekday(Weekday::Mon)
+        );
+        assert_eq!(
+            parse_time_point("Friday").unwrap(),
+            TimePoint::Weekday(Weekday::Fri)
+        );
+    }
+
+    #[test]
+    fn parse_time_point_last_weekday() {
+        assert_eq!(
+            parse_time_point("last-fri").unwrap(),
+            TimePoint::LastWeekday(Weekday::Fri)
+        );
+    }
+
+    #[test]
+    fn parse_time_point_iso_week() {
+        assert_eq!(
+            parse_time_point("2026-W14").unwrap(),
+            TimePoint::IsoWeek(2026, 14)
+        );
+    }
+
+    #[test]
+    fn parse_time_point_invalid() {
+        assert!(parse_time_point("2026-W54").is_err());
+        assert!(parse_time_point("last-month").is_err());
+        assert!(parse_time_point("day+").is_err());
+        assert!(parse_time_point("week+-1").is_err());
+    }
+
+    #[test]
+    fn parse_time_point_rolling_windows() {
+        assert_eq!(
+            parse_time_point("last-7d").unwrap(),
+            TimePoint::Last(7, Period::Day)
+        );
+        assert_eq!(
+            parse_time_point("last-4w").unwrap(),
+            TimePoint::Last(4, Period::Week)
+        );
+        assert_eq!(
+            parse_time_point("last-3m").unwrap(),
+            TimePoint::Last(3, Period::Month)
+        );
+        assert_eq!(
+            parse_time_point("last-7-days").unwrap(),
+            TimePoint::Last(7, Period::Day)
+        );
+    }
+
+    #[test]
+    fn rolling_windows_across_month_ends() {
+        let bounds = |input: &str, today: NaiveDate| {
+            let point = parse_time_point(input).unwrap();
+            (
+                point.to_lower_bound_with_date(today).unwrap(),
+                point.to_upper_bound_with_date(today).unwrap(),
+            )
+        };
+        let march_31 = NaiveDate::from_ymd(2019, 3, 31);
+        assert_eq!(
+            bounds("last-3m", march_31),
+            (NaiveDate::from_ymd(2019, 1, 1), march_31)
+        );
+        assert_eq!(
+            bounds("last-1m", march_31),
+            (NaiveDate::from_ymd(2019, 3, 1), march_31)
+        );
+        assert_eq!(
+            bounds("last-4w", march_31),
+            (NaiveDate::from_ymd(2019, 3, 4), march_31)
+        );
+        let leap_day = NaiveDate::from_ymd(2020, 2, 29);
+        assert_eq!(
+            bounds("last-3m", leap_day),
+            (NaiveDate::from_ymd(2019, 11, 30), leap_day)
+        );
+        assert_eq!(
+            bounds("last-4w", leap_day),
+            (NaiveDate::from_ymd(2020, 2, 2), leap_day)
+        );
+    }
+
+    #[test]
+    fn parse_invalid_rolling_windows() {
+        assert!(parse_time_point("last-0d").is_err());
+        assert!(parse_time_point("last-d").is_err());
+        assert!(parse_time_point("last-7y").is_err());
+        assert!(parse_time_point("last--7d").is_err());
+    }
+
     #[test]
     fn empty_string_range() {
         assert!(TimeRange::parse("").is_err());
@@ -205,6 +534,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn range_without_end() {
+        assert_eq!(
+            TimePointRange::parse("2019-01-23..").unwrap(),
+            TimePointRange {
+                from: TimePoint::Date(NaiveDate::from_ymd(2019, 01, 23)),
+                to: TimePoint::Day(0),
+            }
+        );
+    }
+
+    #[test]
+    fn range_without_start() {
+        assert_eq!(
+            TimePointRange::parse("..today").unwrap(),
+            TimePointRange {
+                from: TimePoint::Epoch,
+                to: TimePoint::Day(0),
+            }
+        );
+    }
+
+    #[test]
+    fn range_without_start_from_epoch() {
+        let settings = RangeSettings {
+            epoch: NaiveDate::from_ymd(2018, 1, 1),
+            ..RangeSettings::default()
+        };
+        assert_eq!(
+            TimeRange::parse_with_settings("..2019-05-09", &settings).unwrap(),
+            TimeRange {
+                from: NaiveDate::from_ymd(2018, 1, 1),
+                to: NaiveDate::from_ymd(2019, 5, 9),
+            }
+        );
+    }
+
+    #[test]
+    fn reversed_range() {
+        match TimeRange::parse("2019-05-09..2019-01-23") {
+            Err(Error::ReversedTimeRange(from, to)) => {
+                assert_eq!(from, NaiveDate::from_ymd(2019, 5, 9));
+                assert_eq!(to, NaiveDate::from_ymd(2019, 1, 23));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn mixed_range() {
         assert_eq!(
@@ -399,4 +776,443 @@ mod tests {
             NaiveDate::from_ymd(2019, 8, 31)
         );
     }
+
+    #[test]
+    fn today_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Day(0)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 24)
+        );
+    }
+
+    #[test]
+    fn today_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Day(0)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 24)
+        );
+    }
+
+    #[test]
+    fn days_ago_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Day(-24)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 7, 31)
+        );
+    }
+
+    #[test]
+    fn days_ago_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Day(-1)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 1, 1))
+                .unwrap(),
+            NaiveDate::from_ymd(2018, 12, 31)
+        );
+    }
+
+    #[test]
+    fn weekday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Weekday(Weekday::Mon)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 19)
+        );
+    }
+
+    #[test]
+    fn weekday_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Weekday(Weekday::Sun)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 19))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 25)
+        );
+    }
+
+    #[test]
+    fn last_weekday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::LastWeekday(Weekday::Fri)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 23)
+        );
+    }
+
+    #[test]
+    fn last_weekday_to_upper_bound() {
+        assert_eq!(
+            TimePoint::LastWeekday(Weekday::Fri)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 19))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 16)
+        );
+    }
+
+    #[test]
+    fn last_weekday_on_same_weekday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::LastWeekday(Weekday::Fri)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 23))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 16)
+        );
+    }
+
+    #[test]
+    fn weekday_ahead_of_today_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Weekday(Weekday::Fri)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 19))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 23)
+        );
+    }
+
+    #[test]
+    fn iso_week_to_lower_bound() {
+        assert_eq!(
+            TimePoint::IsoWeek(2026, 14)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2026, 3, 30)
+        );
+    }
+
+    #[test]
+    fn iso_week_to_upper_bound() {
+        assert_eq!(
+            TimePoint::IsoWeek(2026, 14)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2026, 4, 5)
+        );
+    }
+
+    #[test]
+    fn iso_week_1_to_lower_bound() {
+        assert_eq!(
+            TimePoint::IsoWeek(2026, 1)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2025, 12, 29)
+        );
+    }
+
+    #[test]
+    fn current_quarter_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Quarter(0)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 7, 1)
+        );
+    }
+
+    #[test]
+    fn current_quarter_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Quarter(0)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 9, 30)
+        );
+    }
+
+    #[test]
+    fn last_quarter_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Quarter(-1)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 2, 10))
+                .unwrap(),
+            NaiveDate::from_ymd(2018, 10, 1)
+        );
+    }
+
+    #[test]
+    fn next_quarter_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Quarter(1)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 11, 30))
+                .unwrap(),
+            NaiveDate::from_ymd(2020, 3, 31)
+        );
+    }
+
+    #[test]
+    fn current_year_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Year(0)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 1, 1)
+        );
+    }
+
+    #[test]
+    fn last_year_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Year(-1)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2018, 12, 31)
+        );
+    }
+
+    #[test]
+    fn last_month_in_january_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Month(-1)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 1, 15))
+                .unwrap(),
+            NaiveDate::from_ymd(2018, 12, 1)
+        );
+    }
+
+    #[test]
+    fn last_month_in_january_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Month(-1)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 1, 15))
+                .unwrap(),
+            NaiveDate::from_ymd(2018, 12, 31)
+        );
+    }
+
+    #[test]
+    fn month_plus_3_in_november_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Month(3)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 11, 30))
+                .unwrap(),
+            NaiveDate::from_ymd(2020, 2, 1)
+        );
+    }
+
+    #[test]
+    fn month_plus_3_in_november_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Month(3)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 11, 30))
+                .unwrap(),
+            NaiveDate::from_ymd(2020, 2, 29)
+        );
+    }
+
+    #[test]
+    fn month_from_day_31_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Month(-2)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 31))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 6, 30)
+        );
+    }
+
+    #[test]
+    fn huge_month_offset_is_out_of_range() {
+        assert!(TimePoint::Month(i32::MAX)
+            .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+            .is_err());
+        assert!(TimePoint::Quarter(i32::MIN)
+            .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+            .is_err());
+    }
+
+    fn month_index(date: NaiveDate) -> i32 {
+        date.year() * 12 + date.month0() as i32
+    }
+
+    #[test]
+    fn month_offsets_sweep() {
+        for year in 2015..=2021 {
+            for month in 1..=12 {
+                for &day in &[1, 15, 28] {
+                    let today = NaiveDate::from_ymd(year, month, day);
+                    for offset in -40..=40 {
+                        let from = TimePoint::Month(offset)
+                            .to_lower_bound_with_date(today)
+                            .unwrap();
+                        let to = TimePoint::Month(offset)
+                            .to_upper_bound_with_date(today)
+                            .unwrap();
+                        assert_eq!(from.day(), 1, "month{:+} on {}", offset, today);
+                        assert_eq!(month_index(from), month_index(today) + offset);
+                        assert_eq!(month_index(to), month_index(from));
+                        assert_eq!((to + Duration::days(1)).day(), 1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quarter_and_year_offsets_sweep() {
+        for year in 2015..=2021 {
+            for month in 1..=12 {
+                let today = NaiveDate::from_ymd(year, month, 10);
+                for offset in -10..=10 {
+                    let from = TimePoint::Quarter(offset)
+                        .to_lower_bound_with_date(today)
+                        .unwrap();
+                    let to = TimePoint::Quarter(offset)
+                        .to_upper_bound_with_date(today)
+                        .unwrap();
+                    assert_eq!(from.day(), 1, "quarter{:+} on {}", offset, today);
+                    assert_eq!(from.month0() % 3, 0);
+                    assert_eq!(
+                        month_index(from),
+                        month_index(today) - today.month0() as i32 % 3 + offset * 3
+                    );
+                    assert_eq!(month_index(to), month_index(from) + 2);
+                    assert_eq!((to + Duration::days(1)).day(), 1);
+
+                    let from = TimePoint::Year(offset)
+                        .to_lower_bound_with_date(today)
+                        .unwrap();
+                    let to = TimePoint::Year(offset)
+                        .to_upper_bound_with_date(today)
+                        .unwrap();
+                    assert_eq!(from, NaiveDate::from_ymd(year + offset, 1, 1));
+                    assert_eq!(to, NaiveDate::from_ymd(year + offset, 12, 31));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn current_week_starting_sunday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Week(0)
+                .to_lower_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 24),
+                    &week_starting(Weekday::Sun)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 18)
+        );
+    }
+
+    #[test]
+    fn current_week_starting_sunday_on_sunday_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Week(0)
+                .to_upper_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 25),
+                    &week_starting(Weekday::Sun)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 31)
+        );
+    }
+
+    #[test]
+    fn last_week_starting_saturday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Week(-1)
+                .to_lower_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 23),
+                    &week_starting(Weekday::Sat)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 10)
+        );
+    }
+
+    #[test]
+    fn last_week_starting_saturday_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Week(-1)
+                .to_upper_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 23),
+                    &week_starting(Weekday::Sat)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 16)
+        );
+    }
+
+    #[test]
+    fn weekday_in_week_starting_sunday_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Weekday(Weekday::Sun)
+                .to_lower_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 24),
+                    &week_starting(Weekday::Sun)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 18)
+        );
+    }
+
+    #[test]
+    fn iso_week_ignores_week_start() {
+        assert_eq!(
+            TimePoint::IsoWeek(2026, 14)
+                .to_lower_bound_with_settings(
+                    NaiveDate::from_ymd(2019, 8, 24),
+                    &week_starting(Weekday::Sun)
+                )
+                .unwrap(),
+            NaiveDate::from_ymd(2026, 3, 30)
+        );
+    }
+
+    #[test]
+    fn last_7_days_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Last(7, Period::Day)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 18)
+        );
+    }
+
+    #[test]
+    fn last_7_days_to_upper_bound() {
+        assert_eq!(
+            TimePoint::Last(7, Period::Day)
+                .to_upper_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 8, 24)
+        );
+    }
+
+    #[test]
+    fn last_4_weeks_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Last(4, Period::Week)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 7, 28)
+        );
+    }
+
+    #[test]
+    fn last_3_months_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Last(3, Period::Month)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 8, 24))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 5, 25)
+        );
+    }
+
+    #[test]
+    fn last_3_months_from_month_end_to_lower_bound() {
+        assert_eq!(
+            TimePoint::Last(3, Period::Month)
+                .to_lower_bound_with_date(NaiveDate::from_ymd(2019, 5, 31))
+                .unwrap(),
+            NaiveDate::from_ymd(2019, 3, 1)
+        );
+    }
 }
@@ -0,0 +1,165 @@
+use crate::app_error::{Error, Result};
+use crate::config::config_dir;
+use crate::TimeEntry;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, create_dir_all, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use toml;
+
+const TIMER_FILE: &str = "timer.toml";
+const MAX_HOURS: f32 = 24.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timer {
+    pub issue_id: i32,
+    pub activity_name: String,
+    pub comments: Option<String>,
+    pub started_at: String,
+}
+
+impl Timer {
+    pub fn start(issue_id: i32, activity_name: String, comments: Option<String>) -> Self {
+        Timer {
+            issue_id,
+            activity_name,
+            comments,
+            started_at: Local::now().to_rfc3339(),
+        }
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        if let Ok(mut file) = File::open(timer_path()?) {
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)?;
+            Ok(Some(toml::from_str(&contents)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = timer_path()?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let mut file = File::create(path)?;
+        write!(file, "{}", toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn remove() -> Result<()> {
+        fs::remove_file(timer_path()?)?;
+        Ok(())
+    }
+
+    pub fn started_at(&self) -> Result<DateTime<Local>> {
+        Ok(DateTime::parse_from_rfc3339(&self.started_at)?.with_timezone(&Local))
+    }
+
+    pub fn elapsed(&self) -> Result<Duration> {
+        Ok(Local::now().signed_duration_since(self.started_at()?))
+    }
+
+    /// Checks if the timer was started on an earlier day than today.
+    pub fn crosses_midnight(&self) -> Result<bool> {
+        Ok(crosses_midnight(self.started_at()?, Local::now()))
+    }
+
+    /// Time entry of the elapsed time, failing if it is more than a day.
+    pub fn into_time_entry(self, rounding: Option<f32>) -> Result<TimeEntry> {
+        let spent_on = self.started_at()?.naive_local().date();
+        let hours = logged_hours(self.elapsed()?, rounding)?;
+        Ok(TimeEntry {
+            issue_id: self.issue_id,
+            spent_on,
+            hours,
+            activity_name: self.activity_name,
+            comments: self.comments,
+        })
+    }
+}
+
+fn timer_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(TIMER_FILE))
+}
+
+fn elapsed_hours(elapsed: Duration) -> f32 {
+    elapsed.num_seconds().max(0) as f32 / 3600.0
+}
+
+fn logged_hours(elapsed: Duration, rounding: Option<f32>) -> Result<f32> {
+    let hours = elapsed_hours(elapsed);
+    if hours > MAX_HOURS {
+        return Err(Error::TimerTooLong(hours));
+    }
+    Ok(round_hours(hours, rounding))
+}
+
+fn crosses_midnight(started_at: DateTime<Local>, now: DateTime<Local>) -> bool {
+    started_at.date() != now.date()
+}
+
+/// Rounds hours to the nearest multiple of `rounding`, but never down to zero.
+/// Without rounding, hours are rounded to two decimal places as stored by Redmine.
+pub fn round_hours(hours: f32, rounding: Option<f32>) -> f32 {
+    match rounding {
+        Some(step) if step > 0.0 => ((hours / step).round() * step).max(step),
+        _ => ((hours * 100.0).round() / 100.0).max(0.01),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn elapsed_hours_from_minutes() {
+        assert!((elapsed_hours(Duration::minutes(90)) - 1.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn logged_hours_up_to_a_day() {
+        assert!((logged_hours(Duration::hours(24), None).unwrap() - 24.0).abs() < 0.001);
+        match logged_hours(Duration::hours(60), Some(0.25)) {
+            Err(Error::TimerTooLong(hours)) => assert!((hours - 60.0).abs() < 0.001),
+            _ => panic!("timer running for days accepted"),
+        }
+    }
+
+    #[test]
+    fn timer_crosses_midnight() {
+        let started_at = Local.ymd(2019, 8, 23).and_hms(22, 30, 0);
+        assert!(!crosses_midnight(
+            started_at,
+            Local.ymd(2019, 8, 23).and_hms(23, 59, 0)
+        ));
+        assert!(crosses_midnight(
+            started_at,
+            Local.ymd(2019, 8, 24).and_hms(0, 30, 0)
+        ));
+    }
+
+    #[test]
+    fn round_hours_without_rounding() {
+        assert!((round_hours(1.234, None) - 1.23).abs() < 0.001);
+    }
+
+    #[test]
+    fn round_hours_to_quarter_down() {
+        assert!((round_hours(1.1, Some(0.25)) - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn round_hours_to_quarter_up() {
+        assert!((round_hours(1.15, Some(0.25)) - 1.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn round_hours_never_to_zero() {
+        assert!((round_hours(0.05, Some(0.25)) - 0.25).abs() < 0.001);
+        assert!((round_hours(0.0, None) - 0.01).abs() < 0.001);
+    }
+}